@@ -0,0 +1,174 @@
+//! 访问列表：提前得知一笔交易会触达的账户和存储槽，用来批量预取
+//!
+//! 两种来源：
+//! - 节点的 `eth_createAccessList`（[`ForkSimulator::create_access_list`]），不需要本地有任何状态；
+//! - 本地执行一次并用 [`AccessListTracer`] 记录（[`ForkSimulator::trace_access_list`]），
+//!   适合状态已经大部分在内存中，或者想把得到的列表保存下来复用的场景。
+
+use ethers_core::types::transaction::eip2930::AccessList as eAccessList;
+use ethers_providers::{JsonRpcClient, Middleware};
+use revm::{
+    interpreter::{opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    primitives::{AccessList, AccessListItem, Address, TransactTo, TxEnv, B256},
+    Database, EvmContext, Inspector,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    error::SimulationError,
+    fork_db::FetchRecord,
+    utils::{to_revm_address, to_revm_b256, to_typed_transaction},
+    ForkSimulator,
+};
+
+/// 记录执行中触达的账户和存储槽
+#[derive(Debug, Default)]
+pub struct AccessListTracer {
+    access: BTreeMap<Address, BTreeSet<B256>>,
+}
+
+impl AccessListTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按地址排序的访问列表
+    pub fn access_list(&self) -> AccessList {
+        AccessList(
+            self.access
+                .iter()
+                .map(|(address, slots)| AccessListItem {
+                    address: *address,
+                    storage_keys: slots.iter().copied().collect(),
+                })
+                .collect(),
+        )
+    }
+
+    fn touch(&mut self, address: Address) {
+        self.access.entry(address).or_default();
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Ok(top) = interp.stack().peek(0) else {
+            return;
+        };
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                self.access
+                    .entry(interp.contract.target_address)
+                    .or_default()
+                    .insert(B256::from(top));
+            }
+            opcode::BALANCE
+            | opcode::EXTCODESIZE
+            | opcode::EXTCODECOPY
+            | opcode::EXTCODEHASH
+            | opcode::SELFDESTRUCT => self.touch(Address::from_word(B256::from(top))),
+            _ => {}
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.touch(inputs.target_address);
+        self.touch(inputs.bytecode_address);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if let Some(address) = outcome.address {
+            self.touch(address);
+        }
+        outcome
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 本地执行一次交易并记录访问列表，状态不会写回 `CacheDB`
+    ///
+    /// 结果包含发送者、接收者和出块地址，与 `eth_createAccessList` 的结果不同。
+    pub fn trace_access_list(&mut self, tx_env: TxEnv) -> Result<AccessList, SimulationError> {
+        let mut tracer = AccessListTracer::new();
+        tracer.touch(tx_env.caller);
+        if let TransactTo::Call(to) = tx_env.transact_to {
+            tracer.touch(to);
+        }
+        tracer.touch(self.block_env().coinbase);
+        self.inspect(tx_env, &mut tracer)?;
+        Ok(tracer.access_list())
+    }
+
+    /// 在分叉区块上调用节点的 `eth_createAccessList`
+    pub async fn create_access_list(&self, tx_env: &TxEnv) -> Result<AccessList, SimulationError> {
+        let mut tx_env = tx_env.clone();
+        tx_env.gas_limit = tx_env
+            .gas_limit
+            .min(self.block_env().gas_limit.saturating_to());
+        let tx = to_typed_transaction(&tx_env, self.chain_id());
+        let result = self
+            .client()
+            .create_access_list(&tx, Some(self.block_number().into()))
+            .await?;
+        Ok(to_revm_access_list(result.access_list))
+    }
+
+    /// 预取访问列表中的全部账户和存储槽
+    pub async fn prefetch_access_list(
+        &mut self,
+        access_list: &AccessList,
+    ) -> Result<FetchRecord, SimulationError> {
+        let accounts: Vec<Address> = access_list.0.iter().map(|item| item.address).collect();
+        let storage: Vec<_> = access_list
+            .0
+            .iter()
+            .flat_map(|item| {
+                item.storage_keys
+                    .iter()
+                    .map(|key| (item.address, (*key).into()))
+            })
+            .collect();
+        self.prefetch(&accounts, &storage).await
+    }
+
+    /// 通过 `eth_createAccessList` 得到交易的访问列表并预取，之后执行该交易完全命中内存
+    ///
+    /// 节点返回的列表不包含发送者、接收者和出块地址，这里会一并预取。
+    pub async fn prefetch_for(&mut self, tx_env: &TxEnv) -> Result<FetchRecord, SimulationError> {
+        let mut access_list = self.create_access_list(tx_env).await?;
+        let mut extra = vec![tx_env.caller, self.block_env().coinbase];
+        if let TransactTo::Call(to) = tx_env.transact_to {
+            extra.push(to);
+        }
+        access_list
+            .0
+            .extend(extra.into_iter().map(|address| AccessListItem {
+                address,
+                storage_keys: Vec::new(),
+            }));
+        self.prefetch_access_list(&access_list).await
+    }
+}
+
+fn to_revm_access_list(access_list: eAccessList) -> AccessList {
+    AccessList(
+        access_list
+            .0
+            .into_iter()
+            .map(|item| AccessListItem {
+                address: to_revm_address(item.address),
+                storage_keys: item.storage_keys.into_iter().map(to_revm_b256).collect(),
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,161 @@
+//! 交易包（bundle）模拟：在固定的分叉区块上依次执行一组交易，评估每笔交易的结果和整体收益
+//!
+//! 交易包在快照上执行，结束后回滚，不影响分叉的基础状态，可以对同一状态反复评估不同的交易包。
+
+use ethers_core::types::U256;
+use ethers_providers::JsonRpcClient;
+use revm::primitives::{Address, Bytes, Log, TxEnv, B256, I256, U256 as rU256};
+use std::collections::BTreeMap;
+
+use crate::{
+    erc20::erc20_contract,
+    error::SimulationError,
+    revert::RevertReason,
+    transaction::decode_raw_transaction,
+    utils::{to_ethers_address, to_revm_u256},
+    ForkSimulator,
+};
+
+/// 交易包中的一笔交易
+#[derive(Debug, Clone)]
+pub enum BundleTx {
+    /// `eth_sendRawTransaction` 格式的已签名交易，发送者从签名恢复，
+    /// 按节点的规则校验 basefee、nonce、余额和 gas 上限
+    Signed(Bytes),
+    /// 未签名交易，直接以 `caller` 为发送者按 `eth_call` 的规则执行
+    Unsigned(Box<TxEnv>),
+}
+
+impl From<TxEnv> for BundleTx {
+    fn from(tx_env: TxEnv) -> Self {
+        Self::Unsigned(Box::new(tx_env))
+    }
+}
+
+impl From<Bytes> for BundleTx {
+    fn from(raw: Bytes) -> Self {
+        Self::Signed(raw)
+    }
+}
+
+/// 单笔交易的执行结果
+#[derive(Debug, Clone)]
+pub struct BundleTxResult {
+    /// 已签名交易的哈希
+    pub hash: Option<B256>,
+    pub from: Address,
+    pub success: bool,
+    pub gas_used: u64,
+    pub logs: Vec<Log>,
+    /// 返回数据或回滚数据
+    pub output: Bytes,
+    pub revert_reason: Option<RevertReason>,
+}
+
+/// 整个交易包的执行结果
+#[derive(Debug, Clone)]
+pub struct BundleResult {
+    pub txs: Vec<BundleTxResult>,
+    pub gas_used: u64,
+    /// 关注地址的 ETH 余额变化（包含其支付的 gas 费用）
+    pub eth_delta: I256,
+    /// 关注地址在各代币上的余额变化
+    pub token_deltas: BTreeMap<Address, I256>,
+    /// 出块地址的 ETH 余额变化，即交易包支付给验证者的费用
+    pub coinbase_delta: I256,
+}
+
+impl BundleResult {
+    /// 全部交易都执行成功
+    pub fn all_succeeded(&self) -> bool {
+        self.txs.iter().all(|tx| tx.success)
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 依次执行交易包，统计 `watch` 地址的 ETH 和 `tokens` 余额变化
+    ///
+    /// 回滚的交易照常计入结果；某笔交易未通过校验（nonce、余额等）时整个交易包无效，返回错误。
+    /// 无论成功与否，执行结束后分叉状态都会恢复到执行前。
+    pub fn simulate_bundle(
+        &mut self,
+        txs: &[BundleTx],
+        watch: Address,
+        tokens: &[Address],
+    ) -> Result<BundleResult, SimulationError> {
+        let snapshot = self.snapshot();
+        let result = self.execute_bundle(txs, watch, tokens);
+        self.revert_to(snapshot);
+        result
+    }
+
+    fn execute_bundle(
+        &mut self,
+        txs: &[BundleTx],
+        watch: Address,
+        tokens: &[Address],
+    ) -> Result<BundleResult, SimulationError> {
+        let coinbase = self.block_env().coinbase;
+        let eth_before = self.eth_balance(watch)?;
+        let coinbase_before = self.eth_balance(coinbase)?;
+        let tokens_before = self.token_balances(watch, tokens)?;
+
+        let mut results = Vec::with_capacity(txs.len());
+        for tx in txs {
+            let (hash, receipt) = match tx {
+                BundleTx::Signed(raw) => {
+                    let decoded = decode_raw_transaction(raw)?;
+                    (Some(decoded.hash), self.commit_validated(decoded.tx_env)?)
+                }
+                BundleTx::Unsigned(tx_env) => (None, self.commit((**tx_env).clone())?),
+            };
+            let output = receipt.output();
+            results.push(BundleTxResult {
+                hash,
+                from: receipt.from,
+                success: receipt.success(),
+                gas_used: receipt.gas_used,
+                logs: receipt.logs().to_vec(),
+                revert_reason: (!receipt.success()).then(|| RevertReason::decode(&output)),
+                output,
+            });
+        }
+
+        let tokens_after = self.token_balances(watch, tokens)?;
+        Ok(BundleResult {
+            gas_used: results.iter().map(|tx| tx.gas_used).sum(),
+            txs: results,
+            eth_delta: delta(eth_before, self.eth_balance(watch)?),
+            token_deltas: tokens
+                .iter()
+                .zip(tokens_before.iter().zip(tokens_after))
+                .map(|(token, (before, after))| (*token, delta(*before, after)))
+                .collect(),
+            coinbase_delta: delta(coinbase_before, self.eth_balance(coinbase)?),
+        })
+    }
+
+    fn eth_balance(&mut self, address: Address) -> Result<rU256, SimulationError> {
+        Ok(self.load_account(address)?.balance)
+    }
+
+    fn token_balances(
+        &mut self,
+        holder: Address,
+        tokens: &[Address],
+    ) -> Result<Vec<rU256>, SimulationError> {
+        let contract = erc20_contract();
+        tokens
+            .iter()
+            .map(|token| {
+                let balance: U256 =
+                    self.call_method(&contract, *token, "balanceOf", to_ethers_address(holder))?;
+                Ok(to_revm_u256(balance))
+            })
+            .collect()
+    }
+}
+
+fn delta(before: rU256, after: rU256) -> I256 {
+    I256::from_raw(after.wrapping_sub(before))
+}
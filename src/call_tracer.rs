@@ -0,0 +1,274 @@
+//! 调用追踪：记录执行中的每一次 CALL/STATICCALL/DELEGATECALL/CREATE，组成嵌套的调用树
+//!
+//! 调用树可以按 Foundry `-vvvv` 的缩进格式打印（`Display`），也可以序列化为 JSON，
+//! 用来查看一次模拟交易实际做了什么。
+
+use ethers_providers::JsonRpcClient;
+use revm::{
+    interpreter::{
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, InstructionResult,
+    },
+    primitives::{Address, Bytes, CreateScheme, TxEnv, U256 as rU256},
+    Database, EvmContext, Inspector,
+};
+use serde::Serialize;
+use std::fmt;
+
+use crate::{error::SimulationError, revert::RevertReason, ForkSimulator};
+
+/// 调用类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CallKind {
+    Call,
+    StaticCall,
+    DelegateCall,
+    CallCode,
+    Create,
+    Create2,
+}
+
+/// 调用树中的一次调用
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub kind: CallKind,
+    pub from: Address,
+    /// 被调用的地址；合约创建时为新合约地址，创建失败时为 `None`
+    pub to: Option<Address>,
+    /// 转账金额，DELEGATECALL 和 STATICCALL 为 0
+    pub value: rU256,
+    /// 调用的 gas 上限
+    pub gas: u64,
+    /// 调用内部消耗的 gas，顶层调用不含固有成本和退款
+    pub gas_used: u64,
+    /// 调用数据，合约创建时为初始化代码
+    pub input: Bytes,
+    /// 返回数据或回滚数据，合约创建成功时为部署的代码
+    pub output: Bytes,
+    /// 结束时的指令结果，如 `Return`、`Stop`、`Revert`、`OutOfGas`
+    pub status: String,
+    pub success: bool,
+    /// 回滚原因，只在 `Revert` 时存在
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    pub depth: usize,
+    pub calls: Vec<CallFrame>,
+}
+
+impl CallFrame {
+    fn finish(&mut self, result: &InstructionResult, gas_used: u64, output: &Bytes) {
+        self.gas_used = gas_used;
+        self.output = output.clone();
+        self.status = format!("{result:?}");
+        self.success = result.is_ok();
+        if result.is_revert() {
+            self.revert_reason = Some(RevertReason::decode(output).to_string());
+        }
+    }
+
+    /// 调用的描述，如 `0x…::0x70a08231(0x…)`、`→ new @0x…`
+    fn describe(&self) -> String {
+        let to = self
+            .to
+            .map(|to| to.to_string())
+            .unwrap_or_else(|| "<unknown>".to_owned());
+        let mut line = match self.kind {
+            CallKind::Create | CallKind::Create2 => format!("→ new @{to}"),
+            _ if self.input.is_empty() => format!("{to}::fallback()"),
+            _ if self.input.len() < 4 => format!("{to}::{}", self.input),
+            _ => format!(
+                "{to}::0x{}({})",
+                hex::encode(&self.input[..4]),
+                match &self.input[4..] {
+                    [] => String::new(),
+                    args => format!("0x{}", hex::encode(args)),
+                }
+            ),
+        };
+        if !self.value.is_zero() {
+            line.push_str(&format!(" {{value: {}}}", self.value));
+        }
+        match self.kind {
+            CallKind::StaticCall => line.push_str(" [staticcall]"),
+            CallKind::DelegateCall => line.push_str(" [delegatecall]"),
+            CallKind::CallCode => line.push_str(" [callcode]"),
+            _ => {}
+        }
+        line
+    }
+
+    /// 调用结束的描述，如 `← [Return] 0x…`、`← [Revert] 调用回滚原因`
+    fn describe_return(&self) -> String {
+        let detail = match (&self.revert_reason, self.kind) {
+            (Some(reason), _) => reason.clone(),
+            (None, _) if !self.success || self.output.is_empty() => String::new(),
+            (None, CallKind::Create | CallKind::Create2) => {
+                format!("{} bytes of code", self.output.len())
+            }
+            (None, _) => self.output.to_string(),
+        };
+        format!("← [{}] {detail}", self.status)
+            .trim_end()
+            .to_owned()
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        writeln!(f, "[{}] {}", self.gas_used, self.describe())?;
+        let child_prefix = format!("{prefix}│   ");
+        for call in &self.calls {
+            write!(f, "{prefix}├─ ")?;
+            call.fmt_tree(f, &child_prefix)?;
+        }
+        writeln!(f, "{prefix}└─ {}", self.describe_return())
+    }
+}
+
+impl fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, "")
+    }
+}
+
+/// 记录调用树的 inspector
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    /// 尚未结束的调用，从外到内
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 顶层调用，执行结束后才有值
+    pub fn root(&self) -> Option<&CallFrame> {
+        self.root.as_ref()
+    }
+
+    pub fn into_root(self) -> Option<CallFrame> {
+        self.root
+    }
+
+    fn enter(&mut self, frame: CallFrame) {
+        self.stack.push(frame);
+    }
+
+    fn exit(&mut self, finish: impl FnOnce(&mut CallFrame)) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        finish(&mut frame);
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let kind = match inputs.scheme {
+            CallScheme::Call | CallScheme::ExtCall => CallKind::Call,
+            CallScheme::StaticCall | CallScheme::ExtStaticCall => CallKind::StaticCall,
+            CallScheme::DelegateCall | CallScheme::ExtDelegateCall => CallKind::DelegateCall,
+            CallScheme::CallCode => CallKind::CallCode,
+        };
+        self.enter(CallFrame {
+            kind,
+            from: inputs.caller,
+            to: Some(inputs.target_address),
+            value: inputs.value.transfer().unwrap_or_default(),
+            gas: inputs.gas_limit,
+            gas_used: 0,
+            input: inputs.input.clone(),
+            output: Bytes::new(),
+            status: String::new(),
+            success: false,
+            revert_reason: None,
+            depth: self.stack.len(),
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.exit(|frame| {
+            frame.finish(
+                outcome.instruction_result(),
+                outcome.gas().spent(),
+                outcome.output(),
+            )
+        });
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let kind = match inputs.scheme {
+            CreateScheme::Create => CallKind::Create,
+            CreateScheme::Create2 { .. } => CallKind::Create2,
+        };
+        self.enter(CallFrame {
+            kind,
+            from: inputs.caller,
+            to: None,
+            value: inputs.value,
+            gas: inputs.gas_limit,
+            gas_used: 0,
+            input: inputs.init_code.clone(),
+            output: Bytes::new(),
+            status: String::new(),
+            success: false,
+            revert_reason: None,
+            depth: self.stack.len(),
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.exit(|frame| {
+            frame.to = outcome.address;
+            frame.finish(
+                outcome.instruction_result(),
+                outcome.gas().spent(),
+                outcome.output(),
+            )
+        });
+        outcome
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 按 `eth_call` 的规则执行交易并返回调用树，状态不会写回 `CacheDB`
+    ///
+    /// 交易回滚或异常终止时同样返回调用树；交易未通过校验时返回错误。
+    pub fn trace_calls(&mut self, tx_env: TxEnv) -> Result<CallFrame, SimulationError> {
+        let mut tracer = CallTracer::new();
+        self.inspect(tx_env, &mut tracer)?;
+        tracer
+            .into_root()
+            .ok_or_else(|| SimulationError::Evm("调用追踪没有记录到顶层调用".to_owned()))
+    }
+}
@@ -0,0 +1,126 @@
+//! 把交易结果写回分叉状态，按顺序模拟多笔交易
+//!
+//! [`ForkSimulator::simulate`] 的执行结果会被丢弃；[`ForkSimulator::commit`] 则把执行后的
+//! 状态变化（包括发送者 nonce 递增和 gas 费用扣除）写入 `CacheDB`，
+//! 之后的交易能看到之前交易的效果，例如 approve → swap → swap。
+
+use ethers_providers::JsonRpcClient;
+use revm::primitives::{
+    Address, Bytes, ExecutionResult, Log, Output, ResultAndState, TransactTo, TxEnv,
+};
+
+use crate::{error::SimulationError, ForkSimulator};
+
+/// 已提交交易的回执
+#[derive(Debug, Clone)]
+pub struct TxReceipt {
+    /// 自分叉以来提交的第几笔交易，从 0 开始
+    pub index: usize,
+    pub from: Address,
+    /// 合约创建交易为 `None`
+    pub to: Option<Address>,
+    pub nonce: u64,
+    pub gas_used: u64,
+    /// 自分叉以来所有已提交交易的 gas 总和
+    pub cumulative_gas_used: u64,
+    /// 创建的合约地址
+    pub contract_address: Option<Address>,
+    pub result: ExecutionResult,
+}
+
+impl TxReceipt {
+    /// 执行成功（回滚和异常终止的交易同样会提交，消耗 nonce 和 gas）
+    pub fn success(&self) -> bool {
+        self.result.is_success()
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        self.result.logs()
+    }
+
+    /// 返回数据或回滚数据
+    pub fn output(&self) -> Bytes {
+        self.result.output().cloned().unwrap_or_default()
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 按 `eth_call` 的规则执行交易并把结果写回 `CacheDB`，返回回执
+    ///
+    /// `nonce` 为 `None` 时使用发送者当前的 nonce。交易未通过校验时返回错误且不修改状态；
+    /// 回滚或异常终止的交易仍然会提交。
+    pub fn commit(&mut self, tx_env: TxEnv) -> Result<TxReceipt, SimulationError> {
+        self.commit_with(tx_env, true)
+    }
+
+    /// 按节点打包交易的规则执行并提交：检查 basefee、nonce 和余额，使用交易自带的 gas 上限
+    ///
+    /// 用于已签名的交易，未通过校验时返回 [`SimulationError::InvalidTransaction`] 且不修改状态。
+    pub fn commit_validated(&mut self, tx_env: TxEnv) -> Result<TxReceipt, SimulationError> {
+        self.commit_with(tx_env, false)
+    }
+
+    fn commit_with(
+        &mut self,
+        mut tx_env: TxEnv,
+        eth_call: bool,
+    ) -> Result<TxReceipt, SimulationError> {
+        let from = tx_env.caller;
+        let nonce = match tx_env.nonce {
+            Some(nonce) => nonce,
+            None => self.load_account(from)?.nonce,
+        };
+        tx_env.nonce = Some(nonce);
+        let to = match tx_env.transact_to {
+            TransactTo::Call(to) => Some(to),
+            TransactTo::Create => None,
+        };
+
+        let ResultAndState { result, state } = if eth_call {
+            self.eth_call_evm(tx_env).transact()?
+        } else {
+            self.evm(tx_env).transact()?
+        };
+        self.commit_state(state);
+        let gas_used = result.gas_used();
+        let contract_address = match &result {
+            ExecutionResult::Success {
+                output: Output::Create(_, address),
+                ..
+            } => *address,
+            _ => None,
+        };
+        let receipt = TxReceipt {
+            index: self.receipts.len(),
+            from,
+            to,
+            nonce,
+            gas_used,
+            cumulative_gas_used: self.cumulative_gas_used() + gas_used,
+            contract_address,
+            result,
+        };
+        self.receipts.push(receipt.clone());
+        Ok(receipt)
+    }
+
+    /// 依次提交多笔交易，遇到校验失败的交易时停止并返回错误
+    pub fn commit_all(
+        &mut self,
+        txs: impl IntoIterator<Item = TxEnv>,
+    ) -> Result<Vec<TxReceipt>, SimulationError> {
+        txs.into_iter().map(|tx_env| self.commit(tx_env)).collect()
+    }
+
+    /// 自分叉以来提交过的交易回执
+    pub fn receipts(&self) -> &[TxReceipt] {
+        &self.receipts
+    }
+
+    fn cumulative_gas_used(&self) -> u64 {
+        self.receipts
+            .last()
+            .map(|receipt| receipt.cumulative_gas_used)
+            .unwrap_or_default()
+    }
+}
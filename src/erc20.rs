@@ -0,0 +1,244 @@
+//! ERC-20 余额和授权存储槽的自动发现与覆盖
+//!
+//! 不同代币把 `balanceOf` / `allowance` 映射放在不同的槽位（代理合约、Vyper 合约的布局也不同）。
+//! 这里在分叉 EVM 中执行一次 `balanceOf` / `allowance`，记录代币合约存储上的 SLOAD，
+//! 再把读取到的槽位与常见映射布局逐一比对，最后写入哨兵值验证，找到真正的映射槽位。
+
+use ethers_contract::BaseContract;
+use ethers_core::abi::parse_abi;
+use ethers_providers::JsonRpcClient;
+use revm::{
+    interpreter::{opcode, Interpreter},
+    primitives::{address, keccak256, Address, Bytes, TransactTo, TxEnv, U256 as rU256},
+    Database, EvmContext, Inspector,
+};
+
+use crate::{
+    error::SimulationError,
+    utils::{mapping_slot, nested_mapping_slot, to_ethers_address},
+    ForkSimulator,
+};
+
+/// 搜索映射声明位置时尝试的最大槽位
+const MAX_MAPPING_INDEX: u64 = 256;
+
+/// 用于探测的持有者和授权对象，不会与真实账户冲突
+const PROBE_OWNER: Address = address!("5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a");
+const PROBE_SPENDER: Address = address!("a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5");
+
+/// 写入候选槽位用来验证的哨兵值
+const SENTINEL: rU256 = rU256::from_limbs([0x1234_5678_9abc_def0, 0x0fed_cba9, 0, 0]);
+
+pub fn erc20_contract() -> BaseContract {
+    BaseContract::from(
+        parse_abi(&[
+            "function balanceOf(address owner) external view returns (uint256)",
+            "function allowance(address owner, address spender) external view returns (uint256)",
+            "function approve(address spender, uint256 amount) external returns (bool)",
+            "function transfer(address to, uint256 amount) external returns (bool)",
+        ])
+        .expect("ERC-20 ABI 声明有效"),
+    )
+}
+
+/// 映射的存储布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingLayout {
+    /// `keccak256(key . index)`
+    Solidity,
+    /// `keccak256(index . key)`
+    Vyper,
+}
+
+/// 映射声明所在的槽位以及布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappingSlot {
+    pub index: rU256,
+    pub layout: MappingLayout,
+}
+
+impl MappingSlot {
+    /// Solidity 布局的映射
+    pub fn solidity(index: u64) -> Self {
+        Self {
+            index: rU256::from(index),
+            layout: MappingLayout::Solidity,
+        }
+    }
+
+    /// `mapping(address => ...)` 中 `key` 的存储槽
+    pub fn slot(&self, key: Address) -> rU256 {
+        match self.layout {
+            MappingLayout::Solidity => mapping_slot(key, self.index),
+            MappingLayout::Vyper => vyper_mapping_slot(self.index, key),
+        }
+    }
+
+    /// `mapping(address => mapping(address => ...))` 中 `(outer, inner)` 的存储槽
+    pub fn nested_slot(&self, outer: Address, inner: Address) -> rU256 {
+        match self.layout {
+            MappingLayout::Solidity => nested_mapping_slot(outer, inner, self.index),
+            MappingLayout::Vyper => {
+                vyper_mapping_slot(vyper_mapping_slot(self.index, outer), inner)
+            }
+        }
+    }
+}
+
+fn vyper_mapping_slot(index: rU256, key: Address) -> rU256 {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(&index.to_be_bytes::<32>());
+    buf[44..].copy_from_slice(key.as_slice());
+    keccak256(buf).into()
+}
+
+/// 已发现的代币存储槽
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Erc20Slots {
+    pub balance: Option<MappingSlot>,
+    pub allowance: Option<MappingSlot>,
+}
+
+/// 记录某个合约存储上被 SLOAD 读取过的槽位（按首次读取顺序）
+///
+/// 以存储所属地址判断，因此代理合约 delegatecall 到实现合约后的读取同样会被记录。
+#[derive(Debug)]
+pub struct SloadTracer {
+    target: Address,
+    pub slots: Vec<rU256>,
+}
+
+impl SloadTracer {
+    pub fn new(target: Address) -> Self {
+        Self {
+            target,
+            slots: Vec::new(),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for SloadTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if interp.current_opcode() != opcode::SLOAD || interp.contract.target_address != self.target
+        {
+            return;
+        }
+        if let Ok(slot) = interp.stack().peek(0) {
+            if !self.slots.contains(&slot) {
+                self.slots.push(slot);
+            }
+        }
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 发现代币 `balanceOf` 映射的槽位，结果会缓存
+    pub fn find_balance_slot(&mut self, token: Address) -> Result<MappingSlot, SimulationError> {
+        if let Some(slot) = self.erc20_slots.get(&token).and_then(|s| s.balance) {
+            return Ok(slot);
+        }
+        let calldata: Bytes = erc20_contract()
+            .encode("balanceOf", to_ethers_address(PROBE_OWNER))?
+            .0
+            .into();
+        let slot = self.find_mapping_slot(token, calldata, |mapping| mapping.slot(PROBE_OWNER))?;
+        self.erc20_slots.entry(token).or_default().balance = Some(slot);
+        Ok(slot)
+    }
+
+    /// 发现代币 `allowance` 映射的槽位，结果会缓存
+    pub fn find_allowance_slot(&mut self, token: Address) -> Result<MappingSlot, SimulationError> {
+        if let Some(slot) = self.erc20_slots.get(&token).and_then(|s| s.allowance) {
+            return Ok(slot);
+        }
+        let calldata: Bytes = erc20_contract()
+            .encode(
+                "allowance",
+                (
+                    to_ethers_address(PROBE_OWNER),
+                    to_ethers_address(PROBE_SPENDER),
+                ),
+            )?
+            .0
+            .into();
+        let slot = self.find_mapping_slot(token, calldata, |mapping| {
+            mapping.nested_slot(PROBE_OWNER, PROBE_SPENDER)
+        })?;
+        self.erc20_slots.entry(token).or_default().allowance = Some(slot);
+        Ok(slot)
+    }
+
+    /// 通过存储覆盖设置 `holder` 的代币余额
+    pub fn set_balance(
+        &mut self,
+        token: Address,
+        holder: Address,
+        amount: rU256,
+    ) -> Result<(), SimulationError> {
+        let slot = self.find_balance_slot(token)?.slot(holder);
+        self.insert_account_storage(token, slot, amount)
+    }
+
+    /// 通过存储覆盖设置 `owner` 给 `spender` 的授权额度
+    pub fn set_allowance(
+        &mut self,
+        token: Address,
+        owner: Address,
+        spender: Address,
+        amount: rU256,
+    ) -> Result<(), SimulationError> {
+        let slot = self.find_allowance_slot(token)?.nested_slot(owner, spender);
+        self.insert_account_storage(token, slot, amount)
+    }
+
+    /// 执行 `calldata` 并在读取过的槽位中寻找与 `expected` 计算结果一致、且写入哨兵值后
+    /// 调用返回值随之改变的映射
+    fn find_mapping_slot(
+        &mut self,
+        token: Address,
+        calldata: Bytes,
+        expected: impl Fn(&MappingSlot) -> rU256,
+    ) -> Result<MappingSlot, SimulationError> {
+        let mut tracer = SloadTracer::new(token);
+        self.inspect(
+            TxEnv {
+                transact_to: TransactTo::Call(token),
+                data: calldata.clone(),
+                ..Default::default()
+            },
+            &mut tracer,
+        )?;
+
+        for key in tracer.slots {
+            for layout in [MappingLayout::Solidity, MappingLayout::Vyper] {
+                let found = (0..MAX_MAPPING_INDEX)
+                    .map(|index| MappingSlot {
+                        index: rU256::from(index),
+                        layout,
+                    })
+                    .find(|mapping| expected(mapping) == key);
+                if let Some(mapping) = found {
+                    if self.probe_slot(token, key, calldata.clone())? {
+                        return Ok(mapping);
+                    }
+                }
+            }
+        }
+        Err(SimulationError::MappingSlotNotFound(token))
+    }
+
+    /// 把哨兵值写入 `slot` 后调用，返回值等于哨兵值说明找到了正确的槽位
+    fn probe_slot(
+        &mut self,
+        token: Address,
+        slot: rU256,
+        calldata: Bytes,
+    ) -> Result<bool, SimulationError> {
+        let original = self.load_storage(token, slot)?;
+        self.insert_account_storage(token, slot, SENTINEL)?;
+        let output = self.call(token, calldata);
+        self.insert_account_storage(token, slot, original)?;
+        Ok(matches!(output, Ok(output) if output.len() >= 32
+            && rU256::from_be_slice(&output[..32]) == SENTINEL))
+    }
+}
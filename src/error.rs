@@ -0,0 +1,141 @@
+//! 模拟执行的错误类型
+
+use ethers_core::abi::{Abi, AbiError};
+use ethers_providers::ProviderError;
+use revm::primitives::{
+    Address, Bytes, EVMError, ExecutionResult, HaltReason, InvalidHeader, InvalidTransaction, Log,
+    Output, B256,
+};
+use std::{io, path::PathBuf};
+use thiserror::Error;
+
+use crate::revert::RevertReason;
+
+/// 创建分叉、读取状态或执行交易时的错误
+///
+/// 节点通信失败都是 [`SimulationError::Rpc`]，其余变体来自节点返回的数据、本地校验或执行结果。
+/// 没有“账户不存在”的变体：节点对不存在的账户同样返回 nonce、余额为 0 且没有代码的账户，
+/// 与空账户无法区分，读取这样的账户得到默认值而不是错误。
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    /// 向节点请求状态失败
+    #[error("RPC 请求失败: {0}")]
+    Rpc(#[from] ProviderError),
+    /// 节点上没有该区块，或区块尚未上链
+    #[error("区块不存在: {0}")]
+    BlockNotFound(String),
+    /// 节点上没有该交易或交易回执
+    #[error("交易不存在: {0}")]
+    TransactionNotFound(B256),
+    /// 区块所在的硬分叉尚不支持，见 [`crate::ForkSimulator::spec_id`]
+    #[error("不支持的硬分叉: {0}")]
+    UnsupportedFork(String),
+    /// 读写磁盘缓存失败，包括缓存文件格式错误
+    #[error("读写状态缓存 {} 失败: {source}", path.display())]
+    Cache {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// `EthersDB` 内部使用 `block_in_place`，只能在多线程 tokio 运行时中创建分叉
+    #[error("创建 EthersDB 失败：需要多线程 tokio 运行时")]
+    MultiThreadRuntimeRequired,
+    /// 交易未通过校验（nonce、余额、gas 等），没有执行
+    #[error("交易校验失败: {0}")]
+    InvalidTransaction(#[from] InvalidTransaction),
+    #[error("区块环境校验失败: {0}")]
+    InvalidHeader(#[from] InvalidHeader),
+    /// revm 的其他错误（预编译合约、自定义 handler）
+    #[error("EVM 错误: {0}")]
+    Evm(String),
+    /// 执行回滚，`output` 为原始回滚数据，显示时按 [`RevertReason::decode`] 解码
+    #[error("调用回滚: {}", RevertReason::decode(output))]
+    Revert { output: Bytes, gas_used: u64 },
+    /// 执行异常终止（gas 耗尽、非法指令、栈溢出等）
+    #[error("调用异常终止: {reason:?}")]
+    Halt { reason: HaltReason, gas_used: u64 },
+    /// ABI 编码或解码失败，如参数与函数声明不符、返回数据格式错误
+    #[error("ABI 编解码失败: {0}")]
+    Abi(#[from] AbiError),
+    /// 已签名交易无法解码、无法恢复发送者，或 gas 上限、nonce 超出范围
+    #[error("无法解码已签名交易: {0}")]
+    InvalidRawTransaction(String),
+    /// 不支持的交易类型，如 EIP-7702 交易
+    #[error("不支持的交易类型: {0}")]
+    UnsupportedTransactionType(u64),
+    /// 重放区块中排在前面的交易失败
+    #[error("重放前序交易 {hash} 失败: {source}")]
+    PrecedingTransaction {
+        hash: B256,
+        #[source]
+        source: Box<SimulationError>,
+    },
+    /// 调用参数不合法，如兑换路径过短、代币不属于交易对、数值超出范围
+    #[error("参数无效: {0}")]
+    InvalidArgument(String),
+    /// 没有在代币的存储中找到 `balanceOf` / `allowance` 映射
+    #[error("没有在代币 {0} 的存储中找到对应的映射槽位")]
+    MappingSlotNotFound(Address),
+    /// 执行成功，但结果中缺少预期的内容，如 swap 没有发出 `Swap` 事件
+    #[error("执行结果不符合预期: {0}")]
+    UnexpectedResult(String),
+    /// 状态覆盖不合法，如同时设置了 `state` 和 `stateDiff`
+    #[error("状态覆盖无效: {0}")]
+    InvalidStateOverride(String),
+    /// 估算 gas 时，在允许的最大 gas 上限下交易仍然 gas 不足
+    #[error("所需 gas 超过上限 {0}")]
+    GasRequiredExceedsAllowance(u64),
+}
+
+impl From<EVMError<ProviderError>> for SimulationError {
+    fn from(error: EVMError<ProviderError>) -> Self {
+        match error {
+            EVMError::Transaction(e) => Self::InvalidTransaction(e),
+            EVMError::Header(e) => Self::InvalidHeader(e),
+            EVMError::Database(e) => Self::Rpc(e),
+            EVMError::Custom(e) | EVMError::Precompile(e) => Self::Evm(e),
+        }
+    }
+}
+
+impl SimulationError {
+    /// 回滚时的原始数据
+    pub fn revert_output(&self) -> Option<&Bytes> {
+        match self {
+            Self::Revert { output, .. } => Some(output),
+            _ => None,
+        }
+    }
+
+    /// 解码回滚原因，不是回滚时返回 `None`
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        self.revert_output()
+            .map(|output| RevertReason::decode(output))
+    }
+
+    /// 同 [`SimulationError::revert_reason`]，另外按 `abi` 中的 `error` 声明解码自定义错误
+    pub fn revert_reason_with(&self, abi: &Abi) -> Option<RevertReason> {
+        self.revert_output()
+            .map(|output| RevertReason::decode_with(output, Some(abi)))
+    }
+}
+
+/// 成功执行的结果：输出、gas 消耗和日志
+pub(crate) fn ensure_success(
+    result: ExecutionResult,
+) -> Result<(Output, u64, Vec<Log>), SimulationError> {
+    match result {
+        ExecutionResult::Success {
+            output,
+            gas_used,
+            logs,
+            ..
+        } => Ok((output, gas_used, logs)),
+        ExecutionResult::Revert { output, gas_used } => {
+            Err(SimulationError::Revert { output, gas_used })
+        }
+        ExecutionResult::Halt { reason, gas_used } => {
+            Err(SimulationError::Halt { reason, gas_used })
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! 在分叉上估算交易所需的 gas，规则与 `eth_estimateGas` 一致
+//!
+//! 交易实际消耗的 gas 不等于需要的 gas 上限：存储清零的退款在执行结束后才返还，
+//! 而 CALL 最多只能转出剩余 gas 的 63/64，内层调用需要的 gas 越多，外层需要预留的越多。
+//! 因此在 `[gas_used - 1, 上限]` 之间二分查找能够成功执行的最小 gas 上限。
+
+use ethers_providers::JsonRpcClient;
+use revm::primitives::{ExecutionResult, HaltReason, InvalidTransaction, TxEnv};
+
+use crate::{error::SimulationError, ForkSimulator};
+
+/// 交易的固有成本下限
+const TX_GAS: u64 = 21_000;
+/// 转账调用附带的 gas
+const CALL_STIPEND: u64 = 2_300;
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 按 `eth_call` 的规则估算交易所需的最小 gas 上限，状态不会写回 `CacheDB`
+    ///
+    /// 上限取交易的 `gas_limit`（不低于 21000 时）和区块 gas 上限中较小者，
+    /// 设置了 gas 价格时还不超过发送者余额能支付的数量。以上限执行时回滚返回
+    /// [`SimulationError::Revert`]，gas 仍然不足返回 [`SimulationError::GasRequiredExceedsAllowance`]，
+    /// 其他异常终止返回 [`SimulationError::Halt`]。
+    pub fn estimate_gas(&mut self, tx_env: TxEnv) -> Result<u64, SimulationError> {
+        let block_gas_limit: u64 = self.block_env().gas_limit.saturating_to();
+        let mut hi = if tx_env.gas_limit >= TX_GAS {
+            tx_env.gas_limit.min(block_gas_limit)
+        } else {
+            block_gas_limit
+        };
+        if !tx_env.gas_price.is_zero() {
+            let balance = self.load_account(tx_env.caller)?.balance;
+            let allowance = balance.saturating_sub(tx_env.value) / tx_env.gas_price;
+            hi = hi.min(allowance.saturating_to());
+        }
+        let cap = hi;
+
+        // 先以上限执行：此时失败说明交易本身无法成功，而不是 gas 不够
+        let (gas_used, gas_refunded) = match self.execute_with_gas(&tx_env, hi)? {
+            Some(ExecutionResult::Success {
+                gas_used,
+                gas_refunded,
+                ..
+            }) => (gas_used, gas_refunded),
+            Some(ExecutionResult::Revert { output, gas_used }) => {
+                return Err(SimulationError::Revert { output, gas_used })
+            }
+            Some(ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(_),
+                ..
+            })
+            | None => return Err(SimulationError::GasRequiredExceedsAllowance(cap)),
+            Some(ExecutionResult::Halt { reason, gas_used }) => {
+                return Err(SimulationError::Halt { reason, gas_used })
+            }
+        };
+
+        // 低于实际消耗一定失败；大多数交易只需要在实际消耗（含退款）上按 63/64 预留，先试一次
+        let mut lo = gas_used - 1;
+        let optimistic = (gas_used + gas_refunded + CALL_STIPEND) * 64 / 63;
+        if optimistic < hi {
+            if self.succeeds_with_gas(&tx_env, optimistic)? {
+                hi = optimistic;
+            } else {
+                lo = optimistic;
+            }
+        }
+        // gas 不足时内层调用失败，外层可能以回滚而不是 gas 耗尽结束，因此查找中任何失败都视为 gas 不够
+        while lo + 1 < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.succeeds_with_gas(&tx_env, mid)? {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Ok(hi)
+    }
+
+    fn succeeds_with_gas(
+        &mut self,
+        tx_env: &TxEnv,
+        gas_limit: u64,
+    ) -> Result<bool, SimulationError> {
+        Ok(self
+            .execute_with_gas(tx_env, gas_limit)?
+            .is_some_and(|result| result.is_success()))
+    }
+
+    /// 以指定 gas 上限执行，gas 上限低于固有成本时返回 `None`
+    fn execute_with_gas(
+        &mut self,
+        tx_env: &TxEnv,
+        gas_limit: u64,
+    ) -> Result<Option<ExecutionResult>, SimulationError> {
+        let tx_env = TxEnv {
+            gas_limit,
+            ..tx_env.clone()
+        };
+        match self.simulate(tx_env) {
+            Ok(result) => Ok(Some(result.result)),
+            Err(SimulationError::InvalidTransaction(
+                InvalidTransaction::CallGasCostMoreThanGasLimit,
+            )) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
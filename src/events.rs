@@ -0,0 +1,188 @@
+//! 按 ABI 解码执行结果中的事件日志
+//!
+//! [`LogDecoder::new`] 内置 ERC20（Transfer、Approval）和 Uniswap V2 交易对（Sync、Swap、Mint、Burn）的事件，
+//! 可以再加入其他合约的 ABI。常用事件还可以转换为类型化的 [`KnownEvent`]。
+
+use ethers_core::{
+    abi::{parse_abi, Abi, Event, LogParam, RawLog, Token},
+    types::H256,
+};
+use revm::primitives::{Address, Log, U256 as rU256};
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    signature::format_token,
+    utils::{to_ethers_h256, to_revm_address, to_revm_u256},
+};
+
+/// 内置的事件声明
+const STANDARD_EVENTS: &[&str] = &[
+    "event Transfer(address indexed from, address indexed to, uint256 value)",
+    "event Approval(address indexed owner, address indexed spender, uint256 value)",
+    "event Sync(uint112 reserve0, uint112 reserve1)",
+    "event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+    "event Mint(address indexed sender, uint256 amount0, uint256 amount1)",
+    "event Burn(address indexed sender, uint256 amount0, uint256 amount1, address indexed to)",
+];
+
+/// 按 `topic0` 查找事件声明并解码日志
+#[derive(Debug, Clone)]
+pub struct LogDecoder {
+    /// 同一个 `topic0` 可能对应多个声明（如 ERC20 与 ERC721 的 Transfer），依次尝试
+    events: HashMap<H256, Vec<Event>>,
+}
+
+impl Default for LogDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogDecoder {
+    /// 包含内置事件的解码器
+    pub fn new() -> Self {
+        let abi = parse_abi(STANDARD_EVENTS).expect("内置事件声明有效");
+        Self {
+            events: HashMap::new(),
+        }
+        .with_abi(&abi)
+    }
+
+    /// 加入 ABI 中的全部非匿名事件，后加入的声明优先
+    pub fn add_abi(&mut self, abi: &Abi) {
+        for event in abi.events().filter(|event| !event.anonymous) {
+            self.events
+                .entry(event.signature())
+                .or_default()
+                .insert(0, event.clone());
+        }
+    }
+
+    pub fn with_abi(mut self, abi: &Abi) -> Self {
+        self.add_abi(abi);
+        self
+    }
+
+    /// 解码一条日志，没有匹配的声明或数据不符合声明时返回 `None`
+    pub fn decode(&self, log: &Log) -> Option<DecodedLog> {
+        let topic0 = to_ethers_h256(*log.topics().first()?);
+        self.events.get(&topic0)?.iter().find_map(|event| {
+            let raw = RawLog {
+                topics: log.topics().iter().copied().map(to_ethers_h256).collect(),
+                data: log.data.data.to_vec(),
+            };
+            let parsed = event.parse_log_whole(raw).ok()?;
+            Some(DecodedLog {
+                address: log.address,
+                name: event.name.clone(),
+                params: parsed.params,
+            })
+        })
+    }
+
+    /// 解码全部能识别的日志，保持原有顺序
+    pub fn decode_logs(&self, logs: &[Log]) -> Vec<DecodedLog> {
+        logs.iter().filter_map(|log| self.decode(log)).collect()
+    }
+}
+
+/// 解码后的日志
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedLog {
+    /// 发出日志的合约
+    pub address: Address,
+    /// 事件名
+    pub name: String,
+    pub params: Vec<LogParam>,
+}
+
+impl DecodedLog {
+    /// 按参数名查找参数值
+    pub fn param(&self, name: &str) -> Option<&Token> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| &param.value)
+    }
+
+    /// 转换为类型化的常用事件，不是内置事件时返回 `None`
+    pub fn event(&self) -> Option<KnownEvent> {
+        let address = |name| {
+            self.param(name)
+                .cloned()
+                .and_then(Token::into_address)
+                .map(to_revm_address)
+        };
+        let uint = |name| {
+            self.param(name)
+                .cloned()
+                .and_then(Token::into_uint)
+                .map(to_revm_u256)
+        };
+        Some(match self.name.as_str() {
+            "Transfer" => KnownEvent::Transfer {
+                from: address("from")?,
+                to: address("to")?,
+                value: uint("value")?,
+            },
+            "Approval" => KnownEvent::Approval {
+                owner: address("owner")?,
+                spender: address("spender")?,
+                value: uint("value")?,
+            },
+            "Sync" => KnownEvent::Sync {
+                reserve0: uint("reserve0")?.try_into().ok()?,
+                reserve1: uint("reserve1")?.try_into().ok()?,
+            },
+            "Swap" => KnownEvent::Swap {
+                sender: address("sender")?,
+                amount0_in: uint("amount0In")?,
+                amount1_in: uint("amount1In")?,
+                amount0_out: uint("amount0Out")?,
+                amount1_out: uint("amount1Out")?,
+                to: address("to")?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for DecodedLog {
+    /// 如 `0x…::Sync(reserve0: 1, reserve1: 2)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, format_token(&param.value)))
+            .collect();
+        write!(f, "{}::{}({})", self.address, self.name, params.join(", "))
+    }
+}
+
+/// 类型化的常用事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KnownEvent {
+    /// ERC20 `Transfer`
+    Transfer {
+        from: Address,
+        to: Address,
+        value: rU256,
+    },
+    /// ERC20 `Approval`
+    Approval {
+        owner: Address,
+        spender: Address,
+        value: rU256,
+    },
+    /// Uniswap V2 交易对 `Sync`，值与槽位 8 中的储备量一致
+    Sync { reserve0: u128, reserve1: u128 },
+    /// Uniswap V2 交易对 `Swap`
+    Swap {
+        sender: Address,
+        amount0_in: rU256,
+        amount1_in: rU256,
+        amount0_out: rU256,
+        amount1_out: rU256,
+        to: Address,
+    },
+}
@@ -0,0 +1,151 @@
+use ethers_core::types::BlockId;
+use ethers_providers::{Http, JsonRpcClient, Provider, ProviderError};
+use revm::{
+    db::EthersDB,
+    primitives::{AccountInfo, Address, Bytecode, HashMap, B256, U256 as rU256},
+    DatabaseRef,
+};
+use std::{
+    cell::{Cell, RefCell},
+    sync::Arc,
+};
+
+/// 从节点拉取过的全部数据
+///
+/// 可以用来查看一次模拟实际触达了哪些账户和存储槽，也是磁盘缓存
+/// [`crate::state_cache::StateCache`] 的内容来源。
+#[derive(Debug, Clone, Default)]
+pub struct FetchRecord {
+    pub accounts: HashMap<Address, Option<AccountInfo>>,
+    pub storage: HashMap<Address, HashMap<rU256, rU256>>,
+    pub block_hashes: HashMap<u64, B256>,
+}
+
+impl FetchRecord {
+    /// 记录的存储槽总数
+    pub fn storage_len(&self) -> usize {
+        self.storage.values().map(|slots| slots.len()).sum()
+    }
+}
+
+/// 按需从节点读取状态的分叉数据库
+///
+/// 作为 `CacheDB` 的底层数据库使用：`CacheDB` 未命中的账户、代码、存储槽和区块哈希
+/// 会通过内部的 `EthersDB` 向节点请求，并记录到 [`FetchRecord`] 中。
+/// 之后同一份数据由 `CacheDB` 直接从内存返回，不会重复请求。
+/// 所有读取都固定在创建时指定的区块高度上。
+///
+/// 通过 [`ForkDB::preload`] 预先载入的数据（例如磁盘缓存）会优先使用，命中时不访问节点，
+/// [`ForkDB::network_requests`] 记录了实际发往节点的读取次数。
+#[derive(Debug)]
+pub struct ForkDB<P: JsonRpcClient = Http> {
+    ethersdb: EthersDB<Provider<P>>,
+    block_number: u64,
+    fetched: RefCell<FetchRecord>,
+    network_requests: Cell<usize>,
+}
+
+impl<P: JsonRpcClient + 'static> ForkDB<P> {
+    /// 基于指定区块高度创建
+    ///
+    /// 需要多线程 tokio 运行时，否则返回 `None`。
+    pub fn new(client: Arc<Provider<P>>, block_number: u64) -> Option<Self> {
+        Some(Self {
+            ethersdb: EthersDB::new(client, Some(BlockId::from(block_number)))?,
+            block_number,
+            fetched: RefCell::default(),
+            network_requests: Cell::default(),
+        })
+    }
+
+    /// 载入已知数据，之后对这些账户、存储槽和区块哈希的读取不再访问节点
+    pub fn preload(&mut self, record: FetchRecord) {
+        let fetched = self.fetched.get_mut();
+        fetched.accounts.extend(record.accounts);
+        for (address, slots) in record.storage {
+            fetched.storage.entry(address).or_default().extend(slots);
+        }
+        fetched.block_hashes.extend(record.block_hashes);
+    }
+
+    /// 实际发往节点的读取次数
+    pub fn network_requests(&self) -> usize {
+        self.network_requests.get()
+    }
+
+    /// 分叉所在的区块高度
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    /// 目前为止从节点拉取过的数据
+    pub fn fetched(&self) -> FetchRecord {
+        self.fetched.borrow().clone()
+    }
+}
+
+impl<P: JsonRpcClient + 'static> DatabaseRef for ForkDB<P> {
+    type Error = ProviderError;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.fetched.borrow().accounts.get(&address) {
+            return Ok(info.clone());
+        }
+        self.count_request();
+        let info = self.ethersdb.basic_ref(address)?;
+        self.fetched
+            .borrow_mut()
+            .accounts
+            .insert(address, info.clone());
+        Ok(info)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // EthersDB 在 basic 中已经带回了代码，这里只会在代码未经 basic 加载时被调用
+        self.fetched
+            .borrow()
+            .accounts
+            .values()
+            .flatten()
+            .find(|info| info.code_hash == code_hash)
+            .and_then(|info| info.code.clone())
+            .ok_or_else(|| ProviderError::CustomError(format!("未知的代码哈希: {code_hash}")))
+    }
+
+    fn storage_ref(&self, address: Address, index: rU256) -> Result<rU256, Self::Error> {
+        if let Some(value) = self
+            .fetched
+            .borrow()
+            .storage
+            .get(&address)
+            .and_then(|slots| slots.get(&index))
+        {
+            return Ok(*value);
+        }
+        self.count_request();
+        let value = self.ethersdb.storage_ref(address, index)?;
+        self.fetched
+            .borrow_mut()
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(index, value);
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.fetched.borrow().block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        self.count_request();
+        let hash = self.ethersdb.block_hash_ref(number)?;
+        self.fetched.borrow_mut().block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
+
+impl<P: JsonRpcClient> ForkDB<P> {
+    fn count_request(&self) {
+        self.network_requests.set(self.network_requests.get() + 1);
+    }
+}
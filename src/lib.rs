@@ -0,0 +1,45 @@
+pub mod access_list;
+pub mod bundle;
+pub mod call_tracer;
+pub mod commit;
+pub mod erc20;
+pub mod error;
+pub mod estimate_gas;
+pub mod events;
+pub mod fork_db;
+pub mod prefetch;
+pub mod replay;
+pub mod revert;
+pub mod router;
+pub mod rpc_server;
+pub mod signature;
+pub mod simulator;
+pub mod snapshot;
+pub mod state_cache;
+pub mod state_diff;
+pub mod state_override;
+pub mod struct_log;
+pub mod transaction;
+pub mod tx_replay;
+pub mod uniswap_v2;
+pub mod utils;
+
+pub use access_list::AccessListTracer;
+pub use bundle::{BundleResult, BundleTx, BundleTxResult};
+pub use call_tracer::{CallFrame, CallKind, CallTracer};
+pub use commit::TxReceipt;
+pub use erc20::{Erc20Slots, MappingLayout, MappingSlot, SloadTracer};
+pub use error::SimulationError;
+pub use events::{DecodedLog, KnownEvent, LogDecoder};
+pub use fork_db::{FetchRecord, ForkDB};
+pub use revert::RevertReason;
+pub use router::{RouterHop, RouterQuote, RouterSwap};
+pub use rpc_server::RpcServer;
+pub use signature::Signature;
+pub use simulator::{ForkOptions, ForkSimulator};
+pub use state_cache::StateCache;
+pub use state_diff::{AccountState, StateDiff};
+pub use state_override::{AccountOverride, StateOverride};
+pub use struct_log::{StructLog, StructLogConfig, StructLogTrace, StructLogTracer};
+pub use tx_replay::{TxReplay, TxReplayOptions};
+pub use uniswap_v2::{PairReserves, UniswapV2PairState, V2Swap, V2SwapOutcome};
@@ -1,85 +1,422 @@
-use anyhow::{Ok, Result};
-use bytes::Bytes;
-use ethers_contract::BaseContract;
-use ethers_core::abi::parse_abi;
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use ethers_core::{
+    abi::parse_abi,
+    types::{BlockId, BlockNumber},
+};
 use ethers_providers::{Http, Provider};
 use revm::{
-    db::{CacheDB, EmptyDB, EthersDB},
-    primitives::{ExecutionResult, Output, TransactTo, U256 as rU256},
-    Database, Evm,
+    primitives::{TransactTo, TxEnv, U256 as rU256},
+    Database,
+};
+use revm_example::{
+    signature::format_token, ForkOptions, ForkSimulator, LogDecoder, RpcServer, Signature,
+    StateCache, StateOverride, StructLogConfig, TxReplayOptions,
 };
-use revm_primitives::{Address, TxEnv};
-use std::{env, str::FromStr, sync::Arc};
+use revm_primitives::{Address, B256};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, sync::Arc};
 
 use dotenv::dotenv;
 
+/// 在主网分叉上执行一次性的调用和状态查询
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// 以太坊节点的 RPC 地址，分叉区块的磁盘缓存已经存在时可以省略
+    #[arg(long, env = "HTTP_URL", global = true)]
+    rpc_url: Option<String>,
+    /// 分叉区块：区块高度、区块哈希或 latest/finalized/safe 等标签，默认最新区块；目前只支持 Prague 之前的区块
+    #[arg(long, env = "FORK_BLOCK", global = true, value_parser = BlockId::from_str)]
+    block: Option<BlockId>,
+    /// 链 ID，与缓存目录一起设置且以区块高度分叉时可以完全离线运行
+    #[arg(long, env = "CHAIN_ID", global = true)]
+    chain_id: Option<u64>,
+    /// 磁盘缓存目录
+    #[arg(long, env = "STATE_CACHE_DIR", global = true)]
+    cache_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Query(Query),
+    /// 在本地启动 JSON-RPC 服务，把分叉状态暴露给其他工具
+    Serve {
+        /// 监听地址
+        #[arg(long, default_value = "127.0.0.1:8545")]
+        addr: SocketAddr,
+    },
+    /// 在父区块状态上重放链上交易并与回执比对，忽略 --block
+    Replay {
+        hash: B256,
+        /// 先重放同一区块中排在前面的交易
+        #[arg(long)]
+        preceding: bool,
+    },
+}
+
+/// 在分叉状态上执行一次的调用和查询，结束后保存拉取过的数据
+#[derive(Debug, Subcommand)]
+enum Query {
+    /// 按函数签名编码参数调用合约，并解码返回值
+    ///
+    /// 例如 `call --to <pair> "getReserves()(uint112,uint112,uint32)"`
+    Call {
+        #[arg(long)]
+        to: Address,
+        /// 调用者，默认零地址
+        #[arg(long, default_value_t = Address::ZERO)]
+        from: Address,
+        /// 函数签名，`function` 和 `returns` 可以省略
+        signature: String,
+        /// 函数参数，按签名中的类型解析
+        #[arg(allow_hyphen_values = true)]
+        args: Vec<String>,
+        /// 用于解码回滚数据的自定义错误声明，可重复，如 `--error "InsufficientBalance(uint256,uint256)"`
+        #[arg(long = "error")]
+        errors: Vec<String>,
+        /// 在输出结果之前打印调用树
+        #[arg(long, value_enum)]
+        trace: Option<TraceFormat>,
+        /// 在输出结果之前打印调用造成的状态变化
+        #[arg(long, value_enum)]
+        state_diff: Option<DiffFormat>,
+        /// 在输出结果之前打印调用发出的事件日志
+        #[arg(long)]
+        logs: bool,
+        /// 用于解码日志的事件声明，可重复，如 `--event "Deposit(address indexed,uint256)"`
+        #[arg(long = "event")]
+        events: Vec<String>,
+        /// Geth 风格的状态覆盖，JSON 字符串或 JSON 文件路径，在执行之前写入分叉状态
+        #[arg(long, value_parser = parse_state_override)]
+        state_override: Option<StateOverride>,
+    },
+    /// 按 `eth_estimateGas` 的规则估算调用所需的 gas
+    Estimate {
+        #[arg(long)]
+        to: Address,
+        /// 调用者，默认零地址
+        #[arg(long, default_value_t = Address::ZERO)]
+        from: Address,
+        /// 转账金额（wei）
+        #[arg(long, default_value_t = rU256::ZERO)]
+        value: rU256,
+        /// 函数签名，`function` 和 `returns` 可以省略
+        signature: String,
+        /// 函数参数，按签名中的类型解析
+        #[arg(allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// 读取存储槽
+    Storage {
+        #[arg(long)]
+        to: Address,
+        /// 槽位，十进制或 `0x` 十六进制
+        slot: rU256,
+    },
+    /// 查询账户的余额、nonce 和代码
+    Account {
+        #[arg(long)]
+        to: Address,
+    },
+    /// 直接从存储解码 Uniswap V2 交易对的状态
+    Reserves {
+        #[arg(long)]
+        to: Address,
+    },
+}
+
+/// 调用树的输出格式
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TraceFormat {
+    /// Foundry 风格的缩进树
+    Tree,
+    Json,
+    /// Geth `debug_traceTransaction` 的 structLog 格式
+    StructLogs,
+}
+
+/// 状态差异的输出格式
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DiffFormat {
+    Human,
+    /// 与 Geth `prestateTracer` diff 模式相同的 JSON
+    Json,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    // 先加载 .env，命令行参数未给出时才能回退到其中的环境变量
     dotenv().ok();
-    let https_url = env::var("HTTP_URL").unwrap();
-    let client = Provider::<Http>::try_from(https_url)?;
-    let client = Arc::new(client);
+    let cli = Cli::parse();
+    let options = ForkOptions {
+        fork_block: cli.block,
+        chain_id: cli.chain_id,
+        cache_dir: cli.cache_dir,
+    };
+    // 重放总要向节点查询交易，其他命令在磁盘缓存命中时可以不提供节点地址
+    let rpc_url = match cli.rpc_url {
+        Some(rpc_url) => rpc_url,
+        None if !matches!(cli.command, Command::Replay { .. }) && cache_hit(&options) => {
+            eprintln!("no RPC URL given, running offline from the state cache");
+            OFFLINE_RPC_URL.to_string()
+        }
+        None => bail!(
+            "请通过 --rpc-url、环境变量或 .env 文件设置 HTTP_URL（以太坊节点的 RPC 地址）；\
+             同时设置 --chain-id、数字形式的 --block 和已有缓存的 --cache-dir 时可以离线运行"
+        ),
+    };
+    match cli.command {
+        Command::Replay { hash, preceding } => {
+            let options = TxReplayOptions {
+                replay_preceding: preceding,
+                chain_id: options.chain_id,
+                cache_dir: options.cache_dir,
+            };
+            replay(&rpc_url, hash, options).await
+        }
+        Command::Serve { addr } => {
+            let simulator = fork(&rpc_url, options).await?;
+            let (addr, server) = RpcServer::new(simulator).bind(addr)?;
+            eprintln!("listening on http://{addr}");
+            server.await
+        }
+        Command::Query(query) => {
+            let mut simulator = fork(&rpc_url, options).await?;
+            run_query(&mut simulator, query)?;
+            // 整个过程中实际从节点拉取过的数据
+            let fetched = simulator.fetched();
+            eprintln!(
+                "fetched accounts: {} storage slots: {}",
+                fetched.accounts.len(),
+                fetched.storage_len()
+            );
+            // 把拉取过的数据写入磁盘缓存，下次同一区块的运行不再访问节点
+            if let Some(path) = simulator.save_cache()? {
+                eprintln!("state cache saved to {}", path.display());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// 离线运行时使用的节点地址
+///
+/// 缓存命中时 `ForkDB` 不会发起请求；执行中读到缓存之外的状态时，请求因为 `.invalid`
+/// 域名无法解析而失败，不会访问任何真实的网络地址。
+const OFFLINE_RPC_URL: &str = "http://offline.invalid";
+
+/// 链 ID、分叉高度都已确定，并且缓存目录中已经有该区块的缓存文件
+fn cache_hit(options: &ForkOptions) -> bool {
+    match (&options.cache_dir, options.chain_id, options.fork_block) {
+        (Some(dir), Some(chain_id), Some(BlockId::Number(BlockNumber::Number(number)))) => {
+            StateCache::path(dir, chain_id, number.as_u64()).exists()
+        }
+        _ => false,
+    }
+}
+
+async fn fork(rpc_url: &str, options: ForkOptions) -> Result<ForkSimulator> {
     /////////////////////////////
     ////轻量级"的主网分叉/////////
     ////////////////////////////
+    // ForkSimulator 内部持有 Provider 和 CacheDB<ForkDB>，ForkDB 包装了 EthersDB
     // CacheDB  一个带缓存的数据库实现，可以缓存账户状态和存储数据
-    // EmptyDB  空数据库实现，用于测试或不需要状态的场景
     // EthersDB 与 ethers-rs 库集成的数据库实现，可以从以太坊节点获取数据
-    // 创建EthersDB类型数据库
-    let mut ethersdb = EthersDB::new(client.clone(), None).unwrap();
-    // 将 Uniswap V2 中的 WETH-USDT 池地址设置为pool_address
-    let pool_address = Address::from_str("0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852")?;
-    // 使用ethersdb调用函数“ basic ” 。这将通过异步请求每个节点提供商来检索nonce、余额和代码数据，
-    // 返回类型AccountInfo balance: U256 nonce: u64 code_hash: B256 code: Option<Bytecode>
-    let acc_info = ethersdb.basic(pool_address).unwrap().unwrap();
-    // 8 是 UniswapV2Pair 智能合约中定义的(reserve0, reserve1, blockTimestampLast)变量的存储槽索引。
-    let slot = rU256::from(8);
-    // value 是一个 U256（256位整数），它包含了池子的储备量信息
-    // 在 Uniswap V2 中，槽位 8 存储了三个打包在一起的值 reserve0 reserve1 blockTimestampLast: 最后更新时间
-    // let reserve0 = (value >> 112) & ((1u128 << 112) - 1).into();  // 提取WETH储备量
-    // let reserve1 = (value >> 0) & ((1u128 << 112) - 1).into();    // 提取USDT储备量
-    let value = ethersdb.storage(pool_address, slot).unwrap();
-    println!("{:?}", value); // 0x64ca691b00000000000000001d11899c51780000000003aa5712d4e77e453b6c_U256
-                             // 创建一个空的缓存数据库
-    let mut cache_db = CacheDB::new(EmptyDB::default());
-    // 将合约的基本信息（代码、nonce等）插入缓存
-    cache_db.insert_account_info(pool_address, acc_info);
-    // 将合约的储备量信息插入缓存
-    cache_db.insert_account_storage(pool_address, slot, value);
-    let pool_contract = BaseContract::from(parse_abi(&["function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)"])?);
-    let encoded = pool_contract.encode("getReserves", ())?;
-    let caller = Address::from_str("0x0000000000000000000000000000000000000000")?;
-    // 使用构建器的默认配置
-    let mut evm = Evm::builder()
-        .with_db(cache_db)
-        .with_tx_env(TxEnv {
-            caller,
-            transact_to: TransactTo::Call(pool_address),
-            data: encoded.0.into(),
-            value: rU256::ZERO,
-            ..Default::default()
-        })
-        .build();
-    let ref_tx = evm.transact().unwrap();
-    let result = ref_tx.result;
-    let value = match result {
-        ExecutionResult::Success { output, .. } => match output {
-            Output::Call(value) => Some(value),
-            _ => None,
-        },
-        _ => None,
-    };
-    println!("value====>{:?}", value);
-    // reserve0 reserve1 blockTimestampLast: 最后更新时间
-    let (reserve0, reserve1, ts): (u128, u128, u32) =
-        pool_contract.decode_output("getReserves", value.unwrap())?;
-    // 我们确认“ getReserves ”函数调用返回了我们注入到CacheDB的储备值。
+    let simulator = ForkSimulator::from_url(rpc_url, options).await?;
+    eprintln!("fork block: {}", simulator.block_number());
+    Ok(simulator)
+}
+
+fn run_query(simulator: &mut ForkSimulator, query: Query) -> Result<()> {
+    match query {
+        Query::Call {
+            to,
+            from,
+            signature,
+            args,
+            errors,
+            trace,
+            state_diff,
+            logs,
+            events,
+            state_override,
+        } => {
+            if let Some(overrides) = state_override {
+                simulator.apply_state_override(&overrides)?;
+            }
+            let signature = Signature::parse(&signature)?;
+            let calldata = signature.encode(&args)?;
+            let tx_env = TxEnv {
+                caller: from,
+                transact_to: TransactTo::Call(to),
+                data: calldata.clone(),
+                ..Default::default()
+            };
+            if let Some(format) = trace {
+                // 单独执行一次并挂载追踪器，回滚的调用同样会打印追踪结果
+                let tx_env = tx_env.clone();
+                match format {
+                    TraceFormat::Tree => print!("{}", simulator.trace_calls(tx_env)?),
+                    TraceFormat::Json => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&simulator.trace_calls(tx_env)?)?
+                    ),
+                    TraceFormat::StructLogs => println!(
+                        "{}",
+                        serde_json::to_string_pretty(
+                            &simulator.trace_struct_logs(tx_env, StructLogConfig::default())?
+                        )?
+                    ),
+                }
+            }
+            if logs {
+                let events: Vec<_> = events
+                    .iter()
+                    .map(|e| match e.trim().strip_prefix("event ") {
+                        Some(_) => e.trim().to_string(),
+                        None => format!("event {}", e.trim()),
+                    })
+                    .collect();
+                let abi = parse_abi(&events.iter().map(String::as_str).collect::<Vec<_>>())?;
+                let decoder = LogDecoder::new().with_abi(&abi);
+                let result = simulator.simulate(tx_env.clone())?.result;
+                for log in result.logs() {
+                    match decoder.decode(log) {
+                        Some(decoded) => println!("{decoded}"),
+                        None => println!(
+                            "{}::<unknown>(topics: {:?}, data: {})",
+                            log.address,
+                            log.topics(),
+                            log.data.data
+                        ),
+                    }
+                }
+            }
+            if let Some(format) = state_diff {
+                let (_, diff) = simulator.simulate_diff(tx_env)?;
+                match format {
+                    DiffFormat::Human => print!("{diff}"),
+                    DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+                }
+            }
+            let output = match simulator.call_from(from, to, calldata) {
+                Ok(output) => output,
+                Err(error) => {
+                    let errors: Vec<_> = errors
+                        .iter()
+                        .map(|e| match e.trim().strip_prefix("error ") {
+                            Some(_) => e.trim().to_string(),
+                            None => format!("error {}", e.trim()),
+                        })
+                        .collect();
+                    let abi = parse_abi(&errors.iter().map(String::as_str).collect::<Vec<_>>())?;
+                    if let (Some(reason), Some(output)) =
+                        (error.revert_reason_with(&abi), error.revert_output())
+                    {
+                        bail!("调用回滚: {reason}\n回滚数据: {output}");
+                    }
+                    return Err(error.into());
+                }
+            };
+            for token in signature.decode(&output)? {
+                println!("{}", format_token(&token));
+            }
+        }
+        Query::Estimate {
+            to,
+            from,
+            value,
+            signature,
+            args,
+        } => {
+            let calldata = Signature::parse(&signature)?.encode(&args)?;
+            let gas = simulator.estimate_gas(TxEnv {
+                caller: from,
+                transact_to: TransactTo::Call(to),
+                data: calldata,
+                value,
+                ..Default::default()
+            })?;
+            println!("{gas}");
+        }
+        Query::Storage { to, slot } => {
+            // CacheDB 未命中时由 ForkDB 自动从节点拉取账户（nonce、余额和代码）和存储槽
+            let value = simulator.load_storage(to, slot)?;
+            println!("{value:#066x}");
+        }
+        Query::Account { to } => {
+            let info = simulator
+                .cache_db_mut()
+                .basic(to)?
+                .ok_or_else(|| anyhow!("账户不存在: {to}"))?;
+            println!("balance: {}", info.balance);
+            println!("nonce: {}", info.nonce);
+            println!("code hash: {}", info.code_hash);
+            let code_len = info.code.map(|code| code.len()).unwrap_or_default();
+            println!("code size: {code_len}");
+        }
+        Query::Reserves { to } => {
+            // 在 Uniswap V2 中，槽位 8 存储了三个打包在一起的值，
+            // 从低位开始依次是 reserve0(112位) reserve1(112位) blockTimestampLast(32位)
+            let state = simulator.pair_state(to)?;
+            println!("token0: {}", state.token0);
+            println!("token1: {}", state.token1);
+            println!("reserve0: {}", state.reserves.reserve0);
+            println!("reserve1: {}", state.reserves.reserve1);
+            println!(
+                "blockTimestampLast: {}",
+                state.reserves.block_timestamp_last
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn replay(rpc_url: &str, hash: B256, options: TxReplayOptions) -> Result<()> {
+    let client = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+    let (simulator, replay) = ForkSimulator::replay_tx(client, hash, options).await?;
+    eprintln!(
+        "block: {} index: {} preceding txs replayed: {}",
+        replay.block_number, replay.index, replay.preceding
+    );
+    println!(
+        "success: {} (on-chain {})",
+        replay.success(),
+        replay.expected_success
+    );
+    println!(
+        "gas used: {} (on-chain {})",
+        replay.gas_used(),
+        replay.expected_gas_used
+    );
     println!(
-        "pool_contract.decode_output>>>>>>>>>{:?} {:?} {:?}",
-        reserve0, reserve1, ts
+        "logs: {} (on-chain {})",
+        replay.logs().len(),
+        replay.expected_logs.len()
     );
+    let mismatches = replay.mismatches();
+    if let Some(path) = simulator.save_cache()? {
+        eprintln!("state cache saved to {}", path.display());
+    }
+    if !mismatches.is_empty() {
+        bail!("重放结果与链上不一致:\n{}", mismatches.join("\n"));
+    }
+    println!("replay matches on-chain receipt");
     Ok(())
 }
+
+/// `--state-override` 的参数既可以是内联 JSON，也可以是 JSON 文件路径
+fn parse_state_override(value: &str) -> Result<StateOverride> {
+    let json = if value.trim_start().starts_with('{') {
+        value.to_string()
+    } else {
+        std::fs::read_to_string(value)?
+    };
+    Ok(serde_json::from_str(&json)?)
+}
 // EthersDB:
 // 它不是一个真正的数据库，而是一个数据访问接口
 // 每次调用都会实时从以太坊节点（通过你提供的 RPC URL）获取数据
@@ -0,0 +1,132 @@
+//! 并发预取账户和存储槽
+//!
+//! `EthersDB` 在 EVM 执行中按需同步读取，每个账户 3 个请求、每个存储槽 1 个请求依次发出。
+//! 已知模拟会触达哪些状态时，可以先用 [`ForkSimulator::prefetch`] 并发拉取，
+//! 之后的执行全部命中内存。
+
+use ethers_core::types::{BlockId, H256};
+use ethers_providers::{JsonRpcClient, Middleware};
+use futures::{stream, StreamExt, TryStreamExt};
+use revm::primitives::{AccountInfo, Address, Bytecode, U256 as rU256};
+use std::collections::BTreeSet;
+
+use crate::{
+    error::SimulationError,
+    fork_db::FetchRecord,
+    utils::{to_ethers_address, to_revm_u256},
+    ForkSimulator,
+};
+
+/// 同时进行中的最大请求数
+pub const PREFETCH_CONCURRENCY: usize = 32;
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 并发拉取账户（nonce、余额、代码）和存储槽，并载入 `CacheDB`
+    ///
+    /// 存储槽所属的账户会一并拉取；已经在内存中的账户和存储槽会跳过。
+    /// 返回本次新拉取的数据，这些数据同样会写入 [`ForkSimulator::save_cache`] 的磁盘缓存。
+    /// 预取的请求不计入 [`crate::ForkDB::network_requests`]。
+    pub async fn prefetch(
+        &mut self,
+        accounts: &[Address],
+        storage: &[(Address, rU256)],
+    ) -> Result<FetchRecord, SimulationError> {
+        let known = self.fetched();
+        let cached_account = |address: &Address| {
+            known.accounts.contains_key(address) || self.cache_db().accounts.contains_key(address)
+        };
+        let accounts: BTreeSet<Address> = accounts
+            .iter()
+            .chain(storage.iter().map(|(address, _)| address))
+            .filter(|address| !cached_account(address))
+            .copied()
+            .collect();
+        let storage: BTreeSet<(Address, rU256)> = storage
+            .iter()
+            .filter(|(address, slot)| {
+                let fetched = known
+                    .storage
+                    .get(address)
+                    .is_some_and(|slots| slots.contains_key(slot));
+                let cached = self
+                    .cache_db()
+                    .accounts
+                    .get(address)
+                    .is_some_and(|account| account.storage.contains_key(slot));
+                !fetched && !cached
+            })
+            .copied()
+            .collect();
+
+        let client = self.client().clone();
+        let block = Some(BlockId::from(self.block_number()));
+        let mut record = FetchRecord::default();
+
+        let fetch_accounts = stream::iter(accounts)
+            .map(|address| {
+                let client = &client;
+                async move {
+                    let target = to_ethers_address(address);
+                    let (nonce, balance, code) = futures::try_join!(
+                        client.get_transaction_count(target, block),
+                        client.get_balance(target, block),
+                        client.get_code(target, block),
+                    )?;
+                    let bytecode = Bytecode::new_raw(code.0.into());
+                    let info = AccountInfo::new(
+                        to_revm_u256(balance),
+                        nonce.as_u64(),
+                        bytecode.hash_slow(),
+                        bytecode,
+                    );
+                    Ok::<_, SimulationError>((address, info))
+                }
+            })
+            .buffer_unordered(PREFETCH_CONCURRENCY)
+            .try_collect::<Vec<_>>();
+        let fetch_slots = stream::iter(storage)
+            .map(|(address, slot)| {
+                let client = &client;
+                async move {
+                    let value = client
+                        .get_storage_at(
+                            to_ethers_address(address),
+                            H256::from(slot.to_be_bytes()),
+                            block,
+                        )
+                        .await?;
+                    Ok::<_, SimulationError>((
+                        address,
+                        slot,
+                        rU256::from_be_bytes(value.to_fixed_bytes()),
+                    ))
+                }
+            })
+            .buffer_unordered(PREFETCH_CONCURRENCY)
+            .try_collect::<Vec<_>>();
+        let (fetched_accounts, fetched_slots) = futures::try_join!(fetch_accounts, fetch_slots)?;
+
+        for (address, info) in fetched_accounts {
+            record.accounts.insert(address, Some(info));
+        }
+        for (address, slot, value) in fetched_slots {
+            record
+                .storage
+                .entry(address)
+                .or_default()
+                .insert(slot, value);
+        }
+
+        self.cache_db.db.preload(record.clone());
+        // 从 ForkDB 的内存记录载入 CacheDB，不会访问节点
+        for address in record.accounts.keys() {
+            self.load_account(*address)?;
+        }
+        for (address, slots) in &record.storage {
+            for slot in slots.keys() {
+                self.load_storage(*address, *slot)?;
+            }
+        }
+        Ok(record)
+    }
+}
@@ -0,0 +1,191 @@
+//! 录制与回放 JSON-RPC 响应，用于在没有节点的情况下测试模拟流程
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers_providers::{JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+
+/// 一条录制的 JSON-RPC 请求及其响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    pub result: Value,
+}
+
+/// 读取录制文件（`ReplayEntry` 的 JSON 数组）
+pub fn load_entries(path: impl AsRef<Path>) -> Result<Vec<ReplayEntry>> {
+    let file = fs::File::open(path)?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// 写入录制文件，会自动创建所在目录
+pub fn save_entries(path: impl AsRef<Path>, entries: &[ReplayEntry]) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::File::create(path)?;
+    serde_json::to_writer_pretty(file, entries)?;
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    /// 录制中没有与请求匹配的响应
+    #[error("no recorded response for {method} {params}")]
+    Missing { method: String, params: Value },
+
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
+    /// 被录制的底层传输返回的错误
+    #[error("{0}")]
+    Inner(String),
+}
+
+impl RpcError for ReplayError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        None
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            ReplayError::SerdeJson(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReplayError> for ProviderError {
+    fn from(src: ReplayError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(src))
+    }
+}
+
+/// 按 `(method, params)` 回放录制响应的传输层
+///
+/// 与 ethers 自带的 `MockProvider` 不同，响应按请求内容匹配而不是按顺序弹出，
+/// 因此 `CacheDB` 的访问顺序变化不会影响测试。没有匹配的录制时返回
+/// [`ReplayError::Missing`]，不会访问网络。
+#[derive(Debug, Clone, Default)]
+pub struct ReplayClient {
+    entries: Arc<Mutex<Vec<ReplayEntry>>>,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl ReplayClient {
+    pub fn new(entries: Vec<ReplayEntry>) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(entries)),
+            requests: Arc::default(),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(load_entries(path)?))
+    }
+
+    /// 追加一条录制，同一请求后追加的响应优先
+    pub fn insert(&self, method: &str, params: Value, result: Value) {
+        self.entries.lock().unwrap().push(ReplayEntry {
+            method: method.to_owned(),
+            params,
+            result,
+        });
+    }
+
+    /// 收到过的全部请求，按顺序排列
+    pub fn requests(&self) -> Vec<(String, Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ReplayClient {
+    type Error = ReplayError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ReplayError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method.to_owned(), params.clone()));
+        let result = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|entry| entry.method == method && entry.params == params)
+            .map(|entry| entry.result.clone())
+            .ok_or_else(|| ReplayError::Missing {
+                method: method.to_owned(),
+                params,
+            })?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+/// 包装真实传输层并录制所有成功的请求，用来生成 [`ReplayClient`] 的录制文件
+#[derive(Debug, Clone)]
+pub struct RecordingClient<C> {
+    inner: C,
+    entries: Arc<Mutex<Vec<ReplayEntry>>>,
+}
+
+impl<C: JsonRpcClient> RecordingClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            entries: Arc::default(),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<ReplayEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        save_entries(path, &self.entries())
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for RecordingClient<C> {
+    type Error = ReplayError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, ReplayError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+        // 无参数的请求（如 eth_chainId）序列化为 null，转发时需要保持不带 params
+        let result: Value = if params.is_null() {
+            self.inner.request(method, ()).await
+        } else {
+            self.inner.request(method, &params).await
+        }
+        .map_err(|e| ReplayError::Inner(e.to_string()))?;
+        self.entries.lock().unwrap().push(ReplayEntry {
+            method: method.to_owned(),
+            params,
+            result: result.clone(),
+        });
+        Ok(serde_json::from_value(result)?)
+    }
+}
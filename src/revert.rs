@@ -0,0 +1,105 @@
+//! 回滚原因解码：`Error(string)`、`Panic(uint256)` 以及 ABI 中声明的自定义错误
+
+use ethers_core::abi::{decode, Abi, ParamType, Token};
+use revm::primitives::{Bytes, U256 as rU256};
+use std::fmt;
+
+use crate::{signature::format_token, utils::to_revm_u256};
+
+/// `Error(string)` 的选择器
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// `Panic(uint256)` 的选择器
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// 解码后的回滚原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevertReason {
+    /// 没有回滚数据，例如 `revert()` 或不带信息的 `require`
+    Empty,
+    /// `require(cond, "reason")` / `revert("reason")`
+    Error(String),
+    /// 编译器插入的检查失败，见 [`panic_meaning`]
+    Panic(rU256),
+    /// ABI 中声明的自定义错误
+    Custom { name: String, args: Vec<Token> },
+    /// 无法识别的回滚数据
+    Unknown(Bytes),
+}
+
+impl RevertReason {
+    /// 只识别 `Error(string)` 和 `Panic(uint256)`
+    pub fn decode(output: &[u8]) -> Self {
+        Self::decode_with(output, None)
+    }
+
+    /// 另外按 `abi` 中的 `error` 声明解码自定义错误
+    ///
+    /// `abi` 可以直接用 `parse_abi(&["error InsufficientBalance(uint256 available, uint256 required)"])` 构造。
+    pub fn decode_with(output: &[u8], abi: Option<&Abi>) -> Self {
+        if output.is_empty() {
+            return Self::Empty;
+        }
+        if output.len() < 4 {
+            return Self::Unknown(Bytes::copy_from_slice(output));
+        }
+        let (selector, data) = output.split_at(4);
+        if selector == ERROR_SELECTOR {
+            if let Ok(tokens) = decode(&[ParamType::String], data) {
+                if let Some(Token::String(reason)) = tokens.into_iter().next() {
+                    return Self::Error(reason);
+                }
+            }
+        } else if selector == PANIC_SELECTOR {
+            if let Ok(tokens) = decode(&[ParamType::Uint(256)], data) {
+                if let Some(Token::Uint(code)) = tokens.into_iter().next() {
+                    return Self::Panic(to_revm_u256(code));
+                }
+            }
+        } else if let Some(abi) = abi {
+            let custom = abi
+                .errors()
+                .filter(|error| &error.signature()[..4] == selector)
+                .find_map(|error| Some((error.name.clone(), error.decode(data).ok()?)));
+            if let Some((name, args)) = custom {
+                return Self::Custom { name, args };
+            }
+        }
+        Self::Unknown(Bytes::copy_from_slice(output))
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "没有回滚数据"),
+            Self::Error(reason) => write!(f, "{reason}"),
+            Self::Panic(code) => match panic_meaning(*code) {
+                Some(meaning) => write!(f, "Panic({code:#04x}): {meaning}"),
+                None => write!(f, "Panic({code:#04x})"),
+            },
+            Self::Custom { name, args } => {
+                let args = args.iter().map(format_token).collect::<Vec<_>>();
+                write!(f, "{name}({})", args.join(", "))
+            }
+            Self::Unknown(output) => write!(f, "{output}"),
+        }
+    }
+}
+
+/// Solidity `Panic(uint256)` 错误码的含义
+pub fn panic_meaning(code: rU256) -> Option<&'static str> {
+    let meaning = match code.try_into().ok()? {
+        0x00u64 => "编译器插入的通用 panic",
+        0x01 => "assert 失败",
+        0x11 => "算术运算上溢或下溢",
+        0x12 => "除以零或对零取模",
+        0x21 => "转换为无效的枚举值",
+        0x22 => "访问了编码错误的存储字节数组",
+        0x31 => "对空数组调用 pop()",
+        0x32 => "数组下标越界",
+        0x41 => "分配的内存过多或数组过大",
+        0x51 => "调用了未初始化的内部函数",
+        _ => return None,
+    };
+    Some(meaning)
+}
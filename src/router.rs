@@ -0,0 +1,234 @@
+//! 通过 UniswapV2Router02 在分叉上报价和模拟多跳兑换
+
+use ethers_contract::BaseContract;
+use ethers_core::{abi::parse_abi, types::U256};
+use ethers_providers::JsonRpcClient;
+use revm::primitives::{address, Address, TransactTo, TxEnv, U256 as rU256};
+
+use crate::{
+    call_tracer::CallTracer,
+    erc20::MappingSlot,
+    error::SimulationError,
+    simulator::call_output,
+    utils::{to_ethers_address, to_ethers_addresses, to_ethers_u256, to_revm_u256},
+    ForkSimulator,
+};
+
+/// 以太坊主网 UniswapV2Router02
+pub const UNISWAP_V2_ROUTER02: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+
+/// 交易对 `swap(uint256,uint256,address,bytes)` 的函数选择器
+const PAIR_SWAP_SELECTOR: [u8; 4] = [0x02, 0x2c, 0x0d, 0x9f];
+
+fn router_contract() -> BaseContract {
+    BaseContract::from(parse_abi(&[
+        "function getAmountsOut(uint amountIn, address[] path) external view returns (uint[] amounts)",
+        "function getAmountsIn(uint amountOut, address[] path) external view returns (uint[] amounts)",
+        "function swapExactTokensForTokens(uint amountIn, uint amountOutMin, address[] path, address to, uint deadline) external returns (uint[] amounts)",
+    ])
+    .expect("Router ABI 声明有效"))
+}
+
+fn ensure_path(path: &[Address]) -> Result<(), SimulationError> {
+    if path.len() < 2 {
+        return Err(SimulationError::InvalidArgument(
+            "兑换路径至少需要两个代币".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 路径中的一跳
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouterHop {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: rU256,
+    pub amount_out: rU256,
+    /// 这一跳中交易对 `swap` 调用消耗的 gas，报价时为 `None`
+    pub gas_used: Option<u64>,
+}
+
+/// Router 返回的各跳数量以及执行消耗的 gas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouterQuote {
+    pub path: Vec<Address>,
+    /// Router 返回的 `amounts`，与 `path` 一一对应
+    pub amounts: Vec<rU256>,
+    pub gas_used: u64,
+    /// 各跳交易对 `swap` 调用消耗的 gas，从调用追踪中取得，只有 [`ForkSimulator::simulate_router_swap`]
+    /// 会填写
+    ///
+    /// `getAmountsOut` / `getAmountsIn` 只在 Router 内部读取各交易对的储备量，没有按跳划分的调用，
+    /// 报价时这里为空，只有总的 `gas_used`。其余 gas（转入第一个交易对、Router 自身的计算和
+    /// 固有成本）不属于任何一跳。
+    pub hop_gas_used: Vec<u64>,
+}
+
+impl RouterQuote {
+    pub fn hops(&self) -> Vec<RouterHop> {
+        self.path
+            .windows(2)
+            .zip(self.amounts.windows(2))
+            .enumerate()
+            .map(|(index, (tokens, amounts))| RouterHop {
+                token_in: tokens[0],
+                token_out: tokens[1],
+                amount_in: amounts[0],
+                amount_out: amounts[1],
+                gas_used: self.hop_gas_used.get(index).copied(),
+            })
+            .collect()
+    }
+
+    pub fn amount_in(&self) -> rU256 {
+        self.amounts.first().copied().unwrap_or_default()
+    }
+
+    pub fn amount_out(&self) -> rU256 {
+        self.amounts.last().copied().unwrap_or_default()
+    }
+}
+
+/// 通过 Router 执行 `swapExactTokensForTokens` 的参数
+#[derive(Debug, Clone)]
+pub struct RouterSwap {
+    pub router: Address,
+    pub path: Vec<Address>,
+    pub amount_in: rU256,
+    pub amount_out_min: rU256,
+    /// 交易发送者，会通过存储覆盖获得 `amount_in` 个 `path[0]` 代币并授权给 Router
+    pub from: Address,
+    pub to: Address,
+    /// `path[0]` 合约中 `balanceOf` 映射的槽位，`None` 时自动发现
+    pub balance_slot: Option<MappingSlot>,
+    /// `path[0]` 合约中 `allowance` 映射的槽位，`None` 时自动发现
+    pub allowance_slot: Option<MappingSlot>,
+    /// 截止时间，`None` 时使用分叉区块的时间戳
+    pub deadline: Option<rU256>,
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 调用 Router 的 `getAmountsOut`
+    pub fn router_amounts_out(
+        &mut self,
+        router: Address,
+        amount_in: rU256,
+        path: &[Address],
+    ) -> Result<RouterQuote, SimulationError> {
+        self.router_view(router, "getAmountsOut", amount_in, path)
+    }
+
+    /// 调用 Router 的 `getAmountsIn`
+    pub fn router_amounts_in(
+        &mut self,
+        router: Address,
+        amount_out: rU256,
+        path: &[Address],
+    ) -> Result<RouterQuote, SimulationError> {
+        self.router_view(router, "getAmountsIn", amount_out, path)
+    }
+
+    /// 模拟 `swapExactTokensForTokens`，返回 Router 实际给出的各跳数量和各跳消耗的 gas
+    ///
+    /// 发送者的余额和授权通过存储覆盖设置，模拟结束后恢复原值，执行结果不会写回 `CacheDB`。
+    /// 执行时记录调用树，Router 对交易对的 `swap` 调用按顺序对应路径中的各跳。
+    pub fn simulate_router_swap(
+        &mut self,
+        swap: &RouterSwap,
+    ) -> Result<RouterQuote, SimulationError> {
+        ensure_path(&swap.path)?;
+        let token_in = swap.path[0];
+        let deadline = swap.deadline.unwrap_or(self.block_env().timestamp);
+        let contract = router_contract();
+        let calldata = contract.encode(
+            "swapExactTokensForTokens",
+            (
+                to_ethers_u256(swap.amount_in),
+                to_ethers_u256(swap.amount_out_min),
+                to_ethers_addresses(&swap.path),
+                to_ethers_address(swap.to),
+                to_ethers_u256(deadline),
+            ),
+        )?;
+
+        let balance_slot = match swap.balance_slot {
+            Some(mapping) => mapping,
+            None => self.find_balance_slot(token_in)?,
+        };
+        let allowance_slot = match swap.allowance_slot {
+            Some(mapping) => mapping,
+            None => self.find_allowance_slot(token_in)?,
+        };
+        let overrides = [
+            (balance_slot.slot(swap.from), swap.amount_in),
+            (
+                allowance_slot.nested_slot(swap.from, swap.router),
+                swap.amount_in,
+            ),
+        ];
+        let mut originals = Vec::with_capacity(overrides.len());
+        for (slot, value) in overrides {
+            originals.push((slot, self.load_storage(token_in, slot)?));
+            self.insert_account_storage(token_in, slot, value)?;
+        }
+        let mut tracer = CallTracer::new();
+        let executed = self.inspect(
+            TxEnv {
+                caller: swap.from,
+                transact_to: TransactTo::Call(swap.router),
+                data: calldata.0.into(),
+                ..Default::default()
+            },
+            &mut tracer,
+        );
+        for (slot, value) in originals {
+            self.insert_account_storage(token_in, slot, value)?;
+        }
+
+        let (output, gas_used) = call_output(executed?.result)?;
+        let amounts: Vec<U256> = contract.decode_output("swapExactTokensForTokens", output)?;
+        let hop_gas_used = tracer
+            .root()
+            .map(|root| {
+                root.calls
+                    .iter()
+                    .filter(|call| call.input.starts_with(&PAIR_SWAP_SELECTOR))
+                    .map(|call| call.gas_used)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(RouterQuote {
+            path: swap.path.clone(),
+            amounts: amounts.into_iter().map(to_revm_u256).collect(),
+            gas_used,
+            hop_gas_used,
+        })
+    }
+
+    fn router_view(
+        &mut self,
+        router: Address,
+        method: &str,
+        amount: rU256,
+        path: &[Address],
+    ) -> Result<RouterQuote, SimulationError> {
+        ensure_path(path)?;
+        let contract = router_contract();
+        let calldata =
+            contract.encode(method, (to_ethers_u256(amount), to_ethers_addresses(path)))?;
+        let ref_tx = self.simulate(TxEnv {
+            transact_to: TransactTo::Call(router),
+            data: calldata.0.into(),
+            ..Default::default()
+        })?;
+        let (output, gas_used) = call_output(ref_tx.result)?;
+        let amounts: Vec<U256> = contract.decode_output(method, output)?;
+        Ok(RouterQuote {
+            path: path.to_vec(),
+            amounts: amounts.into_iter().map(to_revm_u256).collect(),
+            gas_used,
+            hop_gas_used: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,378 @@
+//! 本地 JSON-RPC 服务：通过 HTTP 暴露分叉状态，类似精简版的 Anvil
+//!
+//! 所有请求共享同一个 [`ForkSimulator`]，读取仍然走 `EthersDB → CacheDB`，
+//! 已有的 ethers 工具只需要把 RPC 地址指向本服务。支持的方法：
+//!
+//! - `eth_call`、`eth_estimateGas`：按 `eth_call` 的规则在当前状态上执行，不写回状态，
+//!   第三个参数可以是 Geth 风格的状态覆盖（见 [`StateOverride`]）；
+//! - `eth_getBalance`、`eth_getStorageAt`、`eth_getCode`、`eth_getTransactionCount`；
+//! - `eth_sendRawTransaction`：解码已签名交易，按节点的规则校验后提交到分叉状态，
+//!   `eth_getTransactionReceipt` 查询回执；
+//! - `evm_snapshot`、`evm_revert`：见 [`ForkSimulator::snapshot`]；
+//! - `eth_chainId`、`net_version`、`eth_blockNumber`。
+//!
+//! 区块参数会被忽略，所有请求都作用于分叉的当前状态；提交的交易都记在分叉区块上。
+
+use anyhow::Result;
+use ethers_core::types::{Log as eLog, TransactionReceipt, U64};
+use ethers_providers::JsonRpcClient;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use revm::{
+    primitives::{AccessList, Address, Bytes, TransactTo, TxEnv, B256, U256 as rU256},
+    Database,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{convert::Infallible, future::Future, net::SocketAddr, sync::Arc};
+use tokio::sync::Mutex;
+
+use crate::{
+    commit::TxReceipt,
+    error::{ensure_success, SimulationError},
+    revert::RevertReason,
+    state_override::StateOverride,
+    transaction::decode_raw_transaction,
+    utils::{to_ethers_address, to_ethers_h256, to_ethers_u256},
+    ForkSimulator,
+};
+
+/// `eth_call`、`eth_estimateGas` 的交易参数
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallRequest {
+    from: Option<Address>,
+    to: Option<Address>,
+    gas: Option<rU256>,
+    gas_price: Option<rU256>,
+    max_fee_per_gas: Option<rU256>,
+    max_priority_fee_per_gas: Option<rU256>,
+    value: Option<rU256>,
+    #[serde(alias = "input")]
+    data: Option<Bytes>,
+    nonce: Option<rU256>,
+    access_list: Option<AccessList>,
+}
+
+impl CallRequest {
+    fn into_tx_env(self) -> TxEnv {
+        TxEnv {
+            caller: self.from.unwrap_or_default(),
+            gas_limit: self.gas.map(|gas| gas.saturating_to()).unwrap_or(u64::MAX),
+            gas_price: self.max_fee_per_gas.or(self.gas_price).unwrap_or_default(),
+            gas_priority_fee: self.max_priority_fee_per_gas,
+            transact_to: match self.to {
+                Some(to) => TransactTo::Call(to),
+                None => TransactTo::Create,
+            },
+            value: self.value.unwrap_or_default(),
+            data: self.data.unwrap_or_default(),
+            nonce: self.nonce.map(|nonce| nonce.saturating_to()),
+            access_list: self.access_list.map(|list| list.0).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+/// JSON-RPC 错误响应
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn invalid_params(error: impl std::fmt::Display) -> Self {
+        Self::new(-32602, format!("invalid params: {error}"))
+    }
+}
+
+impl From<SimulationError> for RpcError {
+    /// 回滚按 Geth 的约定返回错误码 3，`data` 为原始回滚数据
+    fn from(error: SimulationError) -> Self {
+        match error {
+            SimulationError::Revert { output, .. } => Self {
+                code: 3,
+                message: match RevertReason::decode(&output) {
+                    RevertReason::Empty => "execution reverted".to_owned(),
+                    reason => format!("execution reverted: {reason}"),
+                },
+                data: Some(json!(output)),
+            },
+            error => Self::new(-32000, error.to_string()),
+        }
+    }
+}
+
+/// 已提交交易的哈希和实际 gas 价格，按提交顺序与 [`ForkSimulator::receipts`] 对应
+#[derive(Debug, Clone, Copy)]
+struct SentTx {
+    hash: B256,
+    effective_gas_price: rU256,
+}
+
+struct ServerState<P: JsonRpcClient> {
+    simulator: ForkSimulator<P>,
+    sent: Vec<SentTx>,
+}
+
+/// 本地 JSON-RPC 服务，可以克隆后在多个连接间共享
+pub struct RpcServer<P: JsonRpcClient> {
+    state: Arc<Mutex<ServerState<P>>>,
+}
+
+impl<P: JsonRpcClient> Clone for RpcServer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<P: JsonRpcClient + 'static> RpcServer<P> {
+    pub fn new(simulator: ForkSimulator<P>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ServerState {
+                simulator,
+                sent: Vec::new(),
+            })),
+        }
+    }
+
+    /// 监听 `addr`，返回实际监听的地址（端口为 0 时由系统分配）和运行服务的 future
+    pub fn bind(self, addr: SocketAddr) -> Result<(SocketAddr, impl Future<Output = Result<()>>)> {
+        let make_service = make_service_fn(move |_| {
+            let server = self.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.serve_http(request).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        let local_addr = server.local_addr();
+        Ok((local_addr, async move { Ok(server.await?) }))
+    }
+
+    async fn serve_http(&self, request: Request<Body>) -> Response<Body> {
+        if request.method() != Method::POST {
+            return Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::empty())
+                .expect("响应有效");
+        }
+        let response = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => match serde_json::from_slice(&body) {
+                Ok(request) => self.handle(request).await,
+                Err(e) => error_response(Value::Null, RpcError::new(-32700, e.to_string())),
+            },
+            Err(e) => error_response(Value::Null, RpcError::new(-32700, e.to_string())),
+        };
+        Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(response.to_string()))
+            .expect("响应有效")
+    }
+
+    /// 处理一个 JSON-RPC 请求或批量请求，返回响应
+    pub async fn handle(&self, request: Value) -> Value {
+        match request {
+            Value::Array(requests) => {
+                let mut responses = Vec::with_capacity(requests.len());
+                for request in requests {
+                    responses.push(self.handle_one(request).await);
+                }
+                Value::Array(responses)
+            }
+            request => self.handle_one(request).await,
+        }
+    }
+
+    async fn handle_one(&self, request: Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return error_response(id, RpcError::new(-32600, "invalid request"));
+        };
+        let params = match request.get("params") {
+            Some(Value::Array(params)) => params.clone(),
+            None | Some(Value::Null) => Vec::new(),
+            Some(_) => return error_response(id, RpcError::new(-32602, "params must be an array")),
+        };
+        let mut state = self.state.lock().await;
+        match state.dispatch(method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => error_response(id, error),
+        }
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ServerState<P> {
+    fn dispatch(&mut self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        let simulator = &mut self.simulator;
+        let result = match method {
+            "eth_chainId" => json!(format!("{:#x}", simulator.chain_id())),
+            "net_version" => json!(simulator.chain_id().to_string()),
+            "eth_blockNumber" => json!(format!("{:#x}", simulator.block_number())),
+            "eth_call" => {
+                let tx_env = param::<CallRequest>(params, 0)?.into_tx_env();
+                let overrides = optional_param::<StateOverride>(params, 2)?.unwrap_or_default();
+                let result = simulator.simulate_with_override(tx_env, &overrides)?.result;
+                let output = ensure_success(result)?.0.into_data();
+                json!(output)
+            }
+            "eth_estimateGas" => {
+                let tx_env = param::<CallRequest>(params, 0)?.into_tx_env();
+                let overrides = optional_param::<StateOverride>(params, 2)?.unwrap_or_default();
+                let gas = simulator.estimate_gas_with_override(tx_env, &overrides)?;
+                json!(format!("{gas:#x}"))
+            }
+            "eth_getBalance" | "eth_getTransactionCount" | "eth_getCode" => {
+                let address = param::<Address>(params, 0)?;
+                let info = simulator.load_account(address)?;
+                match method {
+                    "eth_getBalance" => json!(format!("{:#x}", info.balance)),
+                    "eth_getTransactionCount" => json!(format!("{:#x}", info.nonce)),
+                    _ => {
+                        let code = match info.code {
+                            Some(code) => code,
+                            None => simulator
+                                .cache_db
+                                .code_by_hash(info.code_hash)
+                                .map_err(SimulationError::from)?,
+                        };
+                        json!(code.original_bytes())
+                    }
+                }
+            }
+            "eth_getStorageAt" => {
+                let address = param::<Address>(params, 0)?;
+                let slot = param::<rU256>(params, 1)?;
+                let value = simulator.load_storage(address, slot)?;
+                json!(B256::from(value))
+            }
+            "eth_sendRawTransaction" => {
+                let raw = param::<Bytes>(params, 0)?;
+                let decoded = decode_raw_transaction(&raw).map_err(RpcError::invalid_params)?;
+                let tx_env = decoded.tx_env;
+                let basefee = simulator.block_env().basefee;
+                let effective_gas_price = match tx_env.gas_priority_fee {
+                    Some(priority_fee) => tx_env.gas_price.min(basefee + priority_fee),
+                    None => tx_env.gas_price,
+                };
+                // 与节点接收交易时一样校验 basefee、nonce、余额和 gas 上限
+                let receipt = simulator.commit_validated(tx_env)?;
+                // 回滚到快照后重新提交的交易会覆盖被撤销的记录
+                self.sent.truncate(receipt.index);
+                self.sent.push(SentTx {
+                    hash: decoded.hash,
+                    effective_gas_price,
+                });
+                json!(decoded.hash)
+            }
+            "eth_getTransactionReceipt" => {
+                let hash = param::<B256>(params, 0)?;
+                let receipts = simulator.receipts();
+                match self
+                    .sent
+                    .iter()
+                    .zip(receipts)
+                    .find(|(sent, _)| sent.hash == hash)
+                {
+                    Some((sent, receipt)) => serde_json::to_value(to_ethers_receipt(
+                        sent,
+                        receipt,
+                        simulator.block_number(),
+                    ))
+                    .expect("回执可以序列化"),
+                    None => Value::Null,
+                }
+            }
+            "evm_snapshot" => json!(format!("{:#x}", simulator.snapshot())),
+            "evm_revert" => {
+                let id = param::<rU256>(params, 0)?;
+                json!(simulator.revert_to(id.saturating_to()))
+            }
+            _ => {
+                return Err(RpcError::new(
+                    -32601,
+                    format!("method {method} not supported"),
+                ))
+            }
+        };
+        Ok(result)
+    }
+}
+
+/// 按位置解析参数
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    let value = params
+        .get(index)
+        .cloned()
+        .ok_or_else(|| RpcError::invalid_params(format!("missing param {index}")))?;
+    serde_json::from_value(value).map_err(RpcError::invalid_params)
+}
+
+/// 可省略的参数，缺失或为 `null` 时返回 `None`
+fn optional_param<T: DeserializeOwned>(
+    params: &[Value],
+    index: usize,
+) -> Result<Option<T>, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => param(params, index).map(Some),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    let mut body = json!({ "code": error.code, "message": error.message });
+    if let Some(data) = error.data {
+        body["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": body })
+}
+
+fn to_ethers_receipt(sent: &SentTx, receipt: &TxReceipt, block_number: u64) -> TransactionReceipt {
+    let transaction_hash = to_ethers_h256(sent.hash);
+    let transaction_index = U64::from(receipt.index);
+    let block_number = Some(U64::from(block_number));
+    TransactionReceipt {
+        transaction_hash,
+        transaction_index,
+        block_number,
+        from: to_ethers_address(receipt.from),
+        to: receipt.to.map(to_ethers_address),
+        cumulative_gas_used: receipt.cumulative_gas_used.into(),
+        gas_used: Some(receipt.gas_used.into()),
+        contract_address: receipt.contract_address.map(to_ethers_address),
+        logs: receipt
+            .logs()
+            .iter()
+            .enumerate()
+            .map(|(index, log)| eLog {
+                address: to_ethers_address(log.address),
+                topics: log.topics().iter().copied().map(to_ethers_h256).collect(),
+                data: log.data.data.0.clone().into(),
+                block_number,
+                transaction_hash: Some(transaction_hash),
+                transaction_index: Some(transaction_index),
+                log_index: Some(index.into()),
+                ..Default::default()
+            })
+            .collect(),
+        status: Some(U64::from(receipt.success() as u64)),
+        effective_gas_price: Some(to_ethers_u256(sent.effective_gas_price)),
+        ..Default::default()
+    }
+}
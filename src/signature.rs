@@ -0,0 +1,175 @@
+//! 人类可读函数签名的解析、参数编码和返回值格式化，供命令行使用
+
+use anyhow::{anyhow, ensure, Result};
+use ethers_contract::BaseContract;
+use ethers_core::{
+    abi::{
+        parse_abi,
+        token::{LenientTokenizer, Tokenizer},
+        Error as AbiError, Function, Token,
+    },
+    types::{I256, U256},
+};
+use revm::primitives::Bytes;
+
+/// 单个函数签名
+///
+/// 同时接受 `parse_abi` 的写法和省略 `function`、`returns` 的简写：
+///
+/// - `function getReserves() external view returns (uint112, uint112, uint32)`
+/// - `getReserves() returns (uint112,uint112,uint32)`
+/// - `balanceOf(address)(uint256)`
+#[derive(Debug, Clone)]
+pub struct Signature {
+    contract: BaseContract,
+    name: String,
+}
+
+impl Signature {
+    pub fn parse(signature: &str) -> Result<Self> {
+        let normalized = normalize(signature)?;
+        let contract = BaseContract::from(parse_abi(&[normalized.as_str()])?);
+        let name = contract
+            .abi()
+            .functions()
+            .next()
+            .ok_or_else(|| anyhow!("签名中没有函数: {signature}"))?
+            .name
+            .clone();
+        Ok(Self { contract, name })
+    }
+
+    pub fn function(&self) -> &Function {
+        // 解析时已确认存在且只有一个函数
+        self.contract.abi().function(&self.name).unwrap()
+    }
+
+    pub fn contract(&self) -> &BaseContract {
+        &self.contract
+    }
+
+    /// 按参数类型解析字符串参数并编码为 calldata
+    ///
+    /// 整数支持十进制和 `0x` 十六进制，数组和元组使用 `[1,2]`、`(0x..,3)` 的写法。
+    pub fn encode(&self, args: &[String]) -> Result<Bytes> {
+        let function = self.function();
+        ensure!(
+            args.len() == function.inputs.len(),
+            "{} 需要 {} 个参数，实际传入 {} 个",
+            self.name,
+            function.inputs.len(),
+            args.len()
+        );
+        let tokens = function
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| {
+                ArgTokenizer::tokenize(&param.kind, arg)
+                    .map_err(|e| anyhow!("参数 {} ({}) 格式错误: {e}", param.name, param.kind))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(function.encode_input(&tokens)?.into())
+    }
+
+    /// 按返回值类型解码调用输出
+    pub fn decode(&self, output: &[u8]) -> Result<Vec<Token>> {
+        Ok(self.function().decode_output(output)?)
+    }
+}
+
+/// 在 [`LenientTokenizer`] 的基础上支持 `0x` 开头的任意长度十六进制整数
+struct ArgTokenizer;
+
+impl Tokenizer for ArgTokenizer {
+    fn tokenize_address(value: &str) -> Result<[u8; 20], AbiError> {
+        LenientTokenizer::tokenize_address(value)
+    }
+
+    fn tokenize_string(value: &str) -> Result<String, AbiError> {
+        LenientTokenizer::tokenize_string(value)
+    }
+
+    fn tokenize_bool(value: &str) -> Result<bool, AbiError> {
+        LenientTokenizer::tokenize_bool(value)
+    }
+
+    fn tokenize_bytes(value: &str) -> Result<Vec<u8>, AbiError> {
+        LenientTokenizer::tokenize_bytes(value)
+    }
+
+    fn tokenize_fixed_bytes(value: &str, len: usize) -> Result<Vec<u8>, AbiError> {
+        LenientTokenizer::tokenize_fixed_bytes(value, len)
+    }
+
+    fn tokenize_uint(value: &str) -> Result<[u8; 32], AbiError> {
+        match value.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16)
+                .map(Into::into)
+                .map_err(|_| AbiError::InvalidData),
+            None => LenientTokenizer::tokenize_uint(value),
+        }
+    }
+
+    fn tokenize_int(value: &str) -> Result<[u8; 32], AbiError> {
+        match value.strip_prefix("0x") {
+            Some(_) => Self::tokenize_uint(value),
+            None => LenientTokenizer::tokenize_int(value),
+        }
+    }
+}
+
+/// 补全 `function` 前缀，并把 `name(..)(..)` 改写为 `name(..) returns (..)`
+fn normalize(signature: &str) -> Result<String> {
+    let signature = signature.trim();
+    let signature = signature.strip_prefix("function ").unwrap_or(signature);
+    let open = signature
+        .find('(')
+        .ok_or_else(|| anyhow!("函数签名缺少参数列表: {signature}"))?;
+    let mut depth = 0usize;
+    let mut close = None;
+    for (i, c) in signature[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(open + i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close.ok_or_else(|| anyhow!("函数签名括号不匹配: {signature}"))?;
+    let (head, rest) = signature.split_at(close + 1);
+    if rest.trim_start().starts_with('(') {
+        Ok(format!("function {head} returns {}", rest.trim_start()))
+    } else {
+        Ok(format!("function {signature}"))
+    }
+}
+
+/// 把解码后的值格式化为便于阅读的字符串：整数用十进制，地址和字节用 `0x` 十六进制
+pub fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{address:?}"),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => format!("{value:?}"),
+        Token::Array(items) | Token::FixedArray(items) => {
+            format!("[{}]", format_tokens(items))
+        }
+        Token::Tuple(items) => format!("({})", format_tokens(items)),
+    }
+}
+
+fn format_tokens(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(format_token)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
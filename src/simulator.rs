@@ -0,0 +1,437 @@
+use ethers_contract::BaseContract;
+use ethers_core::{
+    abi::{Detokenize, Tokenize},
+    types::{Block, BlockId, BlockNumber},
+};
+use ethers_providers::{Http, JsonRpcClient, Middleware, Provider};
+use revm::{
+    db::CacheDB,
+    inspector_handle_register,
+    primitives::{
+        AccountInfo, BlobExcessGasAndPrice, BlockEnv, ExecutionResult, ResultAndState, SpecId,
+        TransactTo, U256 as rU256,
+    },
+    Database, Evm, Inspector,
+};
+use revm_primitives::{Address, Bytes, TxEnv};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::{
+    commit::TxReceipt,
+    erc20::Erc20Slots,
+    error::{ensure_success, SimulationError},
+    fork_db::{FetchRecord, ForkDB},
+    snapshot::{JournalEntry, StateSnapshot},
+    state_cache::StateCache,
+    utils::{to_revm_address, to_revm_b256, to_revm_u256},
+};
+
+/// 创建 [`ForkSimulator`] 的配置
+#[derive(Debug, Clone, Default)]
+pub struct ForkOptions {
+    /// 分叉区块：区块高度、区块哈希或 `finalized` 等标签，`None` 表示最新区块
+    ///
+    /// 目前只支持 Prague 之前的区块（主网 22431084 之前），主网的最新区块会被拒绝，
+    /// 原因见 [`ForkSimulator::spec_id`]。
+    pub fork_block: Option<BlockId>,
+    /// 链 ID，已知时不再向节点查询，离线使用磁盘缓存时需要提供
+    pub chain_id: Option<u64>,
+    /// 磁盘缓存目录，见 [`StateCache`]
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// 轻量级主网分叉模拟器
+///
+/// 持有 `Provider` 以及 EVM 实际执行时使用的 `CacheDB<ForkDB>`。
+/// 执行中访问到的账户、代码和存储槽在 `CacheDB` 未命中时由 [`ForkDB`] 自动从节点拉取，
+/// 不需要手动预先写入。
+///
+/// 分叉区块在创建时解析为具体高度，之后所有状态读取和 `BlockEnv` 都以该区块为准，
+/// 保证同一区块上的模拟结果可复现。
+///
+/// 配置了缓存目录时，创建时会载入该链、该区块的 [`StateCache`]，
+/// 调用 [`ForkSimulator::save_cache`] 把新拉取的数据写回磁盘。
+/// 以区块高度分叉且提供了链 ID 时，缓存完整的情况下整个过程不会访问节点。
+pub struct ForkSimulator<P: JsonRpcClient = Http> {
+    client: Arc<Provider<P>>,
+    /// 模拟器内部直接读取，写入必须经过带日志的方法，见 [`crate::snapshot`]
+    pub(crate) cache_db: CacheDB<ForkDB<P>>,
+    /// 执行交易时使用的区块环境和硬分叉，重放交易时替换为交易所在区块的值
+    pub(crate) block_env: BlockEnv,
+    pub(crate) spec_id: SpecId,
+    /// 分叉区块自身的区块环境和硬分叉，与状态一起写入磁盘缓存
+    fork_block_env: BlockEnv,
+    fork_spec_id: SpecId,
+    chain_id: u64,
+    cache_dir: Option<PathBuf>,
+    /// 已发现的代币余额、授权映射槽位，见 [`ForkSimulator::find_balance_slot`]
+    pub(crate) erc20_slots: HashMap<Address, Erc20Slots>,
+    /// 见 [`ForkSimulator::snapshot`]
+    pub(crate) snapshots: BTreeMap<u64, StateSnapshot>,
+    pub(crate) next_snapshot_id: u64,
+    /// 存在快照时对 `CacheDB` 的写入记录
+    pub(crate) journal: Vec<JournalEntry>,
+    /// 见 [`ForkSimulator::commit`]
+    pub(crate) receipts: Vec<TxReceipt>,
+}
+
+impl ForkSimulator<Http> {
+    /// 通过 HTTP RPC 地址创建模拟器
+    pub async fn from_url(url: &str, options: ForkOptions) -> Result<Self, SimulationError> {
+        let client = Provider::<Http>::try_from(url)
+            .map_err(|e| SimulationError::InvalidArgument(format!("RPC 地址无效 {url}: {e}")))?;
+        Self::new(Arc::new(client), options).await
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 使用已有的 `Provider` 创建模拟器
+    ///
+    /// `EthersDB` 内部使用 `block_in_place`，必须运行在多线程 tokio 运行时中。
+    ///
+    /// 节点请求失败时返回 [`SimulationError::Rpc`]，分叉区块不存在时返回
+    /// [`SimulationError::BlockNotFound`]，硬分叉尚不支持时返回 [`SimulationError::UnsupportedFork`]。
+    pub async fn new(
+        client: Arc<Provider<P>>,
+        options: ForkOptions,
+    ) -> Result<Self, SimulationError> {
+        let block_id = options
+            .fork_block
+            .unwrap_or(BlockId::Number(BlockNumber::Latest));
+        let chain_id = match options.chain_id {
+            Some(chain_id) => chain_id,
+            None => client.get_chainid().await?.as_u64(),
+        };
+        // 以区块高度分叉时可以直接查找缓存，命中则不需要再请求区块头
+        let cached = match (&options.cache_dir, block_id) {
+            (Some(dir), BlockId::Number(BlockNumber::Number(number))) => {
+                StateCache::load(StateCache::path(dir, chain_id, number.as_u64()))?
+            }
+            _ => None,
+        };
+        let (block_number, block_env, spec_id, cached) = match cached {
+            Some(cache) => (
+                cache.block_number,
+                cache.block_env.clone(),
+                cache.spec_id,
+                Some(cache),
+            ),
+            None => {
+                let block = client
+                    .get_block(block_id)
+                    .await?
+                    .ok_or_else(|| SimulationError::BlockNotFound(format!("{block_id:?}")))?;
+                let block_number = block
+                    .number
+                    .ok_or_else(|| {
+                        SimulationError::BlockNotFound(format!("{block_id:?} 尚未上链"))
+                    })?
+                    .as_u64();
+                let cached = match &options.cache_dir {
+                    Some(dir) => StateCache::load(StateCache::path(dir, chain_id, block_number))?,
+                    None => None,
+                };
+                let spec_id = spec_id_from(chain_id, &block)?;
+                (block_number, block_env_from(&block), spec_id, cached)
+            }
+        };
+        let mut fork_db = ForkDB::new(client.clone(), block_number)
+            .ok_or(SimulationError::MultiThreadRuntimeRequired)?;
+        if let Some(cache) = cached {
+            fork_db.preload(cache.to_record());
+        }
+        Ok(Self {
+            client,
+            cache_db: CacheDB::new(fork_db),
+            fork_block_env: block_env.clone(),
+            fork_spec_id: spec_id,
+            block_env,
+            spec_id,
+            chain_id,
+            cache_dir: options.cache_dir,
+            erc20_slots: HashMap::new(),
+            snapshots: BTreeMap::new(),
+            next_snapshot_id: 0,
+            journal: Vec::new(),
+            receipts: Vec::new(),
+        })
+    }
+
+    pub fn client(&self) -> &Arc<Provider<P>> {
+        &self.client
+    }
+
+    pub fn cache_db(&self) -> &CacheDB<ForkDB<P>> {
+        &self.cache_db
+    }
+
+    /// `CacheDB` 的可变引用
+    ///
+    /// 无法得知调用者会修改什么，存在快照时会先复制全部账户供回滚使用，开销与已缓存的状态大小成正比。
+    /// 修改状态优先使用 [`ForkSimulator::insert_account_info`]、[`ForkSimulator::insert_account_storage`]
+    /// 和 [`ForkSimulator::replace_account_storage`]。
+    pub fn cache_db_mut(&mut self) -> &mut CacheDB<ForkDB<P>> {
+        self.journal_all_accounts();
+        &mut self.cache_db
+    }
+
+    /// 分叉所在的区块高度
+    pub fn block_number(&self) -> u64 {
+        self.cache_db.db.block_number()
+    }
+
+    /// 分叉区块所在的硬分叉
+    ///
+    /// London 之后按区块头中各分叉新增的字段判断，更早的区块只支持主网并按激活高度判断。
+    /// 当前的 revm 版本只实现了 Prague 的草案（没有 EIP-7623 的 calldata 最低收费，
+    /// BLS 预编译合约的地址也与最终版本不同），为避免给出错误的 gas 和预编译结果，
+    /// Prague 及之后的区块在创建分叉时返回错误。
+    pub fn spec_id(&self) -> SpecId {
+        self.spec_id
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// 执行交易时使用的区块环境
+    pub fn block_env(&self) -> &BlockEnv {
+        &self.block_env
+    }
+
+    /// 目前为止从节点拉取过的账户、存储槽和区块哈希
+    pub fn fetched(&self) -> FetchRecord {
+        self.cache_db.db.fetched()
+    }
+
+    /// 把目前拉取过的数据写入磁盘缓存，返回缓存文件路径
+    ///
+    /// 缓存中保存的是分叉区块的区块环境，不受执行时区块环境的影响。
+    /// 未配置缓存目录时不做任何事并返回 `None`。
+    pub fn save_cache(&self) -> Result<Option<PathBuf>, SimulationError> {
+        let Some(dir) = &self.cache_dir else {
+            return Ok(None);
+        };
+        let path = StateCache::path(dir, self.chain_id, self.block_number());
+        StateCache::from_record(
+            self.chain_id,
+            self.block_number(),
+            self.fork_block_env.clone(),
+            self.fork_spec_id,
+            &self.fetched(),
+        )
+        .save(&path)?;
+        Ok(Some(path))
+    }
+
+    /// 预先加载账户的 nonce、余额和代码到 `CacheDB`，返回账户信息
+    ///
+    /// 执行时也会按需自动加载，这里只用于提前预热。节点对不存在的账户同样返回
+    /// nonce、余额为 0 且没有代码的账户，与空账户无法区分，因此不存在的账户不是错误。
+    pub fn load_account(&mut self, address: Address) -> Result<AccountInfo, SimulationError> {
+        Ok(self.cache_db.basic(address)?.unwrap_or_default())
+    }
+
+    /// 读取单个存储槽，未命中时从节点拉取并缓存到 `CacheDB`
+    pub fn load_storage(
+        &mut self,
+        address: Address,
+        slot: rU256,
+    ) -> Result<rU256, SimulationError> {
+        Ok(self.cache_db.storage(address, slot)?)
+    }
+
+    /// 覆盖账户的 nonce、余额和代码，存储不变
+    pub fn insert_account_info(&mut self, address: Address, info: AccountInfo) {
+        self.journal_account(address);
+        self.cache_db.insert_account_info(address, info);
+    }
+
+    /// 覆盖单个存储槽，账户未缓存时先从节点加载
+    pub fn insert_account_storage(
+        &mut self,
+        address: Address,
+        slot: rU256,
+        value: rU256,
+    ) -> Result<(), SimulationError> {
+        self.journal_slot(address, slot);
+        Ok(self.cache_db.insert_account_storage(address, slot, value)?)
+    }
+
+    /// 用 `storage` 替换账户的全部存储，未列出的槽位视为 0，不再从节点读取
+    pub fn replace_account_storage(
+        &mut self,
+        address: Address,
+        storage: revm::primitives::HashMap<rU256, rU256>,
+    ) -> Result<(), SimulationError> {
+        self.journal_account(address);
+        self.journal_storage(address);
+        Ok(self.cache_db.replace_account_storage(address, storage)?)
+    }
+
+    /// 在 `CacheDB` 上执行交易，返回执行结果和状态变化（状态不会写回 `CacheDB`）
+    pub fn transact(&mut self, tx_env: TxEnv) -> Result<ResultAndState, SimulationError> {
+        Ok(self.evm(tx_env).transact()?)
+    }
+
+    /// 按 `eth_call` 的规则执行交易：不检查 basefee，gas 上限不超过区块 gas 上限
+    ///
+    /// 适合模拟 gas 价格为 0 的交易，状态同样不会写回 `CacheDB`。
+    pub fn simulate(&mut self, tx_env: TxEnv) -> Result<ResultAndState, SimulationError> {
+        Ok(self.eth_call_evm(tx_env).transact()?)
+    }
+
+    /// 与 [`ForkSimulator::simulate`] 相同，但执行过程中挂载 `inspector`
+    pub fn inspect<'a, I>(
+        &'a mut self,
+        tx_env: TxEnv,
+        inspector: I,
+    ) -> Result<ResultAndState, SimulationError>
+    where
+        I: Inspector<&'a mut CacheDB<ForkDB<P>>>,
+    {
+        let mut evm = self
+            .eth_call_evm(tx_env)
+            .modify()
+            .reset_handler_with_external_context(inspector)
+            .append_handler_register(inspector_handle_register)
+            .build();
+        Ok(evm.transact()?)
+    }
+
+    /// 以零地址为调用者对 `to` 发起调用，返回原始输出
+    ///
+    /// 交易回滚或异常终止时返回错误。
+    pub fn call(&mut self, to: Address, calldata: Bytes) -> Result<Bytes, SimulationError> {
+        self.call_from(Address::ZERO, to, calldata)
+    }
+
+    /// 以 `from` 为调用者对 `to` 发起调用，返回原始输出
+    pub fn call_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        calldata: Bytes,
+    ) -> Result<Bytes, SimulationError> {
+        let ref_tx = self.simulate(TxEnv {
+            caller: from,
+            transact_to: TransactTo::Call(to),
+            data: calldata,
+            value: rU256::ZERO,
+            ..Default::default()
+        })?;
+        let (output, _) = call_output(ref_tx.result)?;
+        Ok(output)
+    }
+
+    /// 按 ABI 编码参数调用合约函数，并解码返回值
+    pub fn call_method<T: Tokenize, D: Detokenize>(
+        &mut self,
+        contract: &BaseContract,
+        to: Address,
+        name: &str,
+        args: T,
+    ) -> Result<D, SimulationError> {
+        let encoded = contract.encode(name, args)?;
+        let value = self.call(to, encoded.0.into())?;
+        Ok(contract.decode_output(name, value)?)
+    }
+
+    /// 按 `eth_call` 规则构造 EVM，见 [`ForkSimulator::simulate`]
+    pub(crate) fn eth_call_evm(
+        &mut self,
+        mut tx_env: TxEnv,
+    ) -> Evm<'_, (), &mut CacheDB<ForkDB<P>>> {
+        tx_env.gas_limit = tx_env
+            .gas_limit
+            .min(self.block_env.gas_limit.saturating_to());
+        let mut evm = self.evm(tx_env);
+        evm.cfg_mut().disable_base_fee = true;
+        evm
+    }
+
+    /// 基于分叉状态和区块环境构造 EVM
+    pub(crate) fn evm(&mut self, tx_env: TxEnv) -> Evm<'_, (), &mut CacheDB<ForkDB<P>>> {
+        let chain_id = self.chain_id;
+        Evm::builder()
+            .with_db(&mut self.cache_db)
+            .with_block_env(self.block_env.clone())
+            .with_tx_env(tx_env)
+            .modify_cfg_env(|cfg| cfg.chain_id = chain_id)
+            .with_spec_id(self.spec_id)
+            .build()
+    }
+}
+
+/// 取出调用成功时的返回数据和 gas 消耗，回滚或异常终止时返回错误
+pub(crate) fn call_output(result: ExecutionResult) -> Result<(Bytes, u64), SimulationError> {
+    let (output, gas_used, _) = ensure_success(result)?;
+    Ok((output.into_data(), gas_used))
+}
+
+/// 主网 London 之前各硬分叉的激活高度，这些区块头没有可以区分硬分叉的字段
+const MAINNET_PRE_LONDON_FORKS: &[(u64, SpecId)] = &[
+    (12_244_000, SpecId::BERLIN),
+    (9_200_000, SpecId::MUIR_GLACIER),
+    (9_069_000, SpecId::ISTANBUL),
+    (7_280_000, SpecId::PETERSBURG),
+    (4_370_000, SpecId::BYZANTIUM),
+    (2_675_000, SpecId::SPURIOUS_DRAGON),
+    (2_463_000, SpecId::TANGERINE),
+    (1_920_000, SpecId::DAO_FORK),
+    (1_150_000, SpecId::HOMESTEAD),
+];
+
+/// 根据区块头构造 `BlockEnv`
+pub(crate) fn block_env_from<T>(block: &Block<T>) -> BlockEnv {
+    let mut block_env = BlockEnv {
+        number: rU256::from(block.number.unwrap_or_default().as_u64()),
+        coinbase: block.author.map(to_revm_address).unwrap_or_default(),
+        timestamp: to_revm_u256(block.timestamp),
+        gas_limit: to_revm_u256(block.gas_limit),
+        basefee: block.base_fee_per_gas.map(to_revm_u256).unwrap_or_default(),
+        difficulty: to_revm_u256(block.difficulty),
+        prevrandao: block.mix_hash.map(to_revm_b256),
+        blob_excess_gas_and_price: None,
+    };
+    if let Some(excess_blob_gas) = block.excess_blob_gas.map(|gas| gas.as_u64()) {
+        block_env.blob_excess_gas_and_price = Some(BlobExcessGasAndPrice::new(excess_blob_gas));
+    }
+    block_env
+}
+
+/// 根据区块头判断硬分叉，避免在历史区块上启用尚未激活的规则
+///
+/// 支持的范围见 [`ForkSimulator::spec_id`]；London 之前的区块头没有可以区分硬分叉的字段，
+/// 其他链上返回错误而不是猜测。
+pub(crate) fn spec_id_from<T>(chain_id: u64, block: &Block<T>) -> Result<SpecId, SimulationError> {
+    let number = block.number.unwrap_or_default().as_u64();
+    if block.other.contains_key("requestsHash") {
+        return Err(SimulationError::UnsupportedFork(format!(
+            "区块 {number} 已激活 Prague，当前的 revm 版本只实现了 Prague 的草案"
+        )));
+    }
+    Ok(if block.excess_blob_gas.is_some() {
+        SpecId::CANCUN
+    } else if block.withdrawals_root.is_some() {
+        SpecId::SHANGHAI
+    } else if block.base_fee_per_gas.is_some() && block.difficulty.is_zero() {
+        SpecId::MERGE
+    } else if block.base_fee_per_gas.is_some() {
+        SpecId::LONDON
+    } else if chain_id == 1 {
+        MAINNET_PRE_LONDON_FORKS
+            .iter()
+            .find(|(activation, _)| number >= *activation)
+            .map(|(_, spec_id)| *spec_id)
+            .unwrap_or(SpecId::FRONTIER)
+    } else {
+        return Err(SimulationError::UnsupportedFork(format!(
+            "区块 {number} 早于 London，只支持按主网的激活高度判断硬分叉（链 ID {chain_id}）"
+        )));
+    })
+}
@@ -0,0 +1,187 @@
+//! 分叉状态的快照与回滚，语义与 Anvil 的 `evm_snapshot` / `evm_revert` 一致
+//!
+//! 快照只记录日志（journal）的长度。存在快照时，对 `CacheDB` 的每次写入都先在日志中保存被覆盖的
+//! 旧值：账户信息、单个存储槽，或在合约创建、自毁和替换存储时保存该账户的全部存储。回滚时按相反
+//! 顺序恢复旧值，开销与快照之后修改的账户和槽位数量成正比，与分叉状态的大小无关；没有快照时不记录日志。
+//!
+//! 日志不记录从节点加载进 `CacheDB` 的数据：这些数据与分叉区块一致，回滚后保留不影响结果，
+//! 再次读取时也不会重新请求节点。[`ForkSimulator::cache_db_mut`] 交出的可变引用无法跟踪，
+//! 存在快照时会退化为复制全部账户，模拟器自身的写入都经过 [`ForkSimulator::insert_account_info`]
+//! 等带日志的方法。
+
+use ethers_providers::JsonRpcClient;
+use revm::{
+    db::{AccountState, DbAccount},
+    primitives::{AccountInfo, Address, BlockEnv, EvmState, HashMap, U256 as rU256},
+};
+
+use crate::ForkSimulator;
+
+/// 快照时的日志长度、回执数量以及区块环境
+#[derive(Debug, Clone)]
+pub(crate) struct StateSnapshot {
+    journal_len: usize,
+    receipts_len: usize,
+    block_env: BlockEnv,
+}
+
+/// 一次写入之前的状态
+#[derive(Debug, Clone)]
+pub(crate) enum JournalEntry {
+    /// 账户信息和状态，`None` 表示写入前账户不在 `CacheDB` 中
+    Account {
+        address: Address,
+        previous: Option<(AccountInfo, AccountState)>,
+    },
+    /// 单个存储槽，`None` 表示写入前该槽位没有缓存
+    Slot {
+        address: Address,
+        slot: rU256,
+        previous: Option<rU256>,
+    },
+    /// 账户的全部存储
+    Storage {
+        address: Address,
+        previous: HashMap<rU256, rU256>,
+    },
+    /// 交出 `CacheDB` 可变引用之前的全部账户
+    Accounts(HashMap<Address, DbAccount>),
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 保存当前状态，返回快照 ID
+    ///
+    /// 只记录日志长度，不复制状态；快照存在期间的写入会额外保存旧值，直到回滚或快照被删除。
+    pub fn snapshot(&mut self) -> u64 {
+        let snapshot = StateSnapshot {
+            journal_len: self.journal.len(),
+            receipts_len: self.receipts.len(),
+            block_env: self.block_env().clone(),
+        };
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.snapshots.insert(id, snapshot);
+        id
+    }
+
+    /// 回滚到快照 `id` 时的状态
+    ///
+    /// 该快照以及之后创建的快照都会被删除，需要再次回滚时重新调用 [`ForkSimulator::snapshot`]。
+    /// 快照不存在时返回 `false` 且不做任何修改。
+    pub fn revert_to(&mut self, id: u64) -> bool {
+        let Some(snapshot) = self.snapshots.remove(&id) else {
+            return false;
+        };
+        self.snapshots.retain(|&other, _| other < id);
+        let entries = self.journal.split_off(snapshot.journal_len);
+        let accounts = &mut self.cache_db.accounts;
+        for entry in entries.into_iter().rev() {
+            match entry {
+                JournalEntry::Account { address, previous } => match previous {
+                    Some((info, account_state)) => {
+                        let account = accounts.entry(address).or_default();
+                        account.info = info;
+                        account.account_state = account_state;
+                    }
+                    None => {
+                        accounts.remove(&address);
+                    }
+                },
+                JournalEntry::Slot {
+                    address,
+                    slot,
+                    previous,
+                } => {
+                    if let Some(account) = accounts.get_mut(&address) {
+                        match previous {
+                            Some(value) => account.storage.insert(slot, value),
+                            None => account.storage.remove(&slot),
+                        };
+                    }
+                }
+                JournalEntry::Storage { address, previous } => {
+                    if let Some(account) = accounts.get_mut(&address) {
+                        account.storage = previous;
+                    }
+                }
+                JournalEntry::Accounts(previous) => *accounts = previous,
+            }
+        }
+        self.receipts.truncate(snapshot.receipts_len);
+        self.block_env = snapshot.block_env;
+        true
+    }
+
+    /// 把交易执行后的状态写入 `CacheDB`，存在快照时先记录被覆盖的账户和槽位
+    pub(crate) fn commit_state(&mut self, state: EvmState) {
+        if !self.snapshots.is_empty() {
+            for (address, account) in &state {
+                if !account.is_touched() {
+                    continue;
+                }
+                self.journal_account(*address);
+                if account.is_selfdestructed() || account.is_created() {
+                    self.journal_storage(*address);
+                } else {
+                    for (slot, value) in &account.storage {
+                        if value.is_changed() {
+                            self.journal_slot(*address, *slot);
+                        }
+                    }
+                }
+            }
+        }
+        revm::DatabaseCommit::commit(&mut self.cache_db, state);
+    }
+
+    pub(crate) fn journal_account(&mut self, address: Address) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        let previous = self
+            .cache_db
+            .accounts
+            .get(&address)
+            .map(|account| (account.info.clone(), account.account_state.clone()));
+        self.journal
+            .push(JournalEntry::Account { address, previous });
+    }
+
+    pub(crate) fn journal_slot(&mut self, address: Address, slot: rU256) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        let previous = self
+            .cache_db
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&slot).copied());
+        self.journal.push(JournalEntry::Slot {
+            address,
+            slot,
+            previous,
+        });
+    }
+
+    pub(crate) fn journal_storage(&mut self, address: Address) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        let previous = self
+            .cache_db
+            .accounts
+            .get(&address)
+            .map(|account| account.storage.clone())
+            .unwrap_or_default();
+        self.journal
+            .push(JournalEntry::Storage { address, previous });
+    }
+
+    pub(crate) fn journal_all_accounts(&mut self) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        self.journal
+            .push(JournalEntry::Accounts(self.cache_db.accounts.clone()));
+    }
+}
@@ -0,0 +1,146 @@
+use revm::primitives::{
+    AccountInfo, Address, BlockEnv, Bytecode, Bytes, SpecId, B256, U256 as rU256,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::SimulationError, fork_db::FetchRecord};
+
+/// 缓存文件中的账户，只保存原始代码，加载时再重新分析
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedAccount {
+    pub balance: rU256,
+    pub nonce: u64,
+    pub code: Bytes,
+}
+
+/// 持久化到磁盘的 RPC 状态缓存
+///
+/// 每个链、每个区块对应一个 JSON 文件（`<dir>/<chain_id>/<block_number>.json`），
+/// 保存区块环境以及通过 [`crate::ForkDB`] 从节点拉取过的账户、存储槽和区块哈希。
+/// 使用 `BTreeMap` 保证输出顺序稳定，便于提交到仓库后查看差异。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateCache {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub block_env: BlockEnv,
+    pub spec_id: SpecId,
+    /// `None` 表示节点上不存在该账户
+    #[serde(default)]
+    pub accounts: BTreeMap<Address, Option<CachedAccount>>,
+    #[serde(default)]
+    pub storage: BTreeMap<Address, BTreeMap<rU256, rU256>>,
+    #[serde(default)]
+    pub block_hashes: BTreeMap<u64, B256>,
+}
+
+impl StateCache {
+    /// 缓存文件路径
+    pub fn path(dir: impl AsRef<Path>, chain_id: u64, block_number: u64) -> PathBuf {
+        dir.as_ref()
+            .join(chain_id.to_string())
+            .join(format!("{block_number}.json"))
+    }
+
+    /// 读取缓存文件，文件不存在时返回 `None`
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, SimulationError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let load = || -> std::io::Result<Self> {
+            let file = fs::File::open(path)?;
+            Ok(serde_json::from_reader(file)?)
+        };
+        load().map(Some).map_err(|source| SimulationError::Cache {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// 写入缓存文件，会自动创建所在目录
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SimulationError> {
+        let path = path.as_ref();
+        let save = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = fs::File::create(path)?;
+            serde_json::to_writer_pretty(file, self)?;
+            Ok(())
+        };
+        save().map_err(|source| SimulationError::Cache {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn from_record(
+        chain_id: u64,
+        block_number: u64,
+        block_env: BlockEnv,
+        spec_id: SpecId,
+        record: &FetchRecord,
+    ) -> Self {
+        let accounts = record
+            .accounts
+            .iter()
+            .map(|(address, info)| {
+                let account = info.as_ref().map(|info| CachedAccount {
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code: info
+                        .code
+                        .as_ref()
+                        .map(Bytecode::original_bytes)
+                        .unwrap_or_default(),
+                });
+                (*address, account)
+            })
+            .collect();
+        let storage = record
+            .storage
+            .iter()
+            .map(|(address, slots)| {
+                let slots = slots.iter().map(|(k, v)| (*k, *v)).collect();
+                (*address, slots)
+            })
+            .collect();
+        Self {
+            chain_id,
+            block_number,
+            block_env,
+            spec_id,
+            accounts,
+            storage,
+            block_hashes: record.block_hashes.iter().map(|(k, v)| (*k, *v)).collect(),
+        }
+    }
+
+    pub fn to_record(&self) -> FetchRecord {
+        let mut record = FetchRecord::default();
+        for (address, account) in &self.accounts {
+            let info = account.as_ref().map(|account| {
+                let bytecode = Bytecode::new_raw(account.code.clone());
+                AccountInfo::new(
+                    account.balance,
+                    account.nonce,
+                    bytecode.hash_slow(),
+                    bytecode,
+                )
+            });
+            record.accounts.insert(*address, info);
+        }
+        for (address, slots) in &self.storage {
+            record
+                .storage
+                .insert(*address, slots.iter().map(|(k, v)| (*k, *v)).collect());
+        }
+        record.block_hashes = self.block_hashes.iter().map(|(k, v)| (*k, *v)).collect();
+        record
+    }
+}
@@ -0,0 +1,183 @@
+//! 状态差异：把执行返回的 `state` 整理为按账户的变化，形状与 Geth `prestateTracer` 的 diff 模式一致
+//!
+//! `pre` 包含被修改账户执行前的余额、nonce、代码，以及被修改存储槽的旧值；
+//! `post` 只包含发生变化的字段和存储槽的新值（值为 0 的存储槽省略）。
+//! 交易中创建的合约不出现在 `pre` 中，自毁的账户不出现在 `post` 中。
+
+use ethers_providers::JsonRpcClient;
+use revm::{
+    primitives::{
+        AccountInfo, Address, Bytes, EvmState, ExecutionResult, TxEnv, B256, U256 as rU256,
+    },
+    Database,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use crate::{error::SimulationError, ForkSimulator};
+
+/// 一个账户在 `pre` 或 `post` 中的状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<rU256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+/// 一笔交易造成的状态差异，JSON 形式与 `prestateTracer` 的 `{"pre": .., "post": ..}` 相同
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub pre: BTreeMap<Address, AccountState>,
+    pub post: BTreeMap<Address, AccountState>,
+}
+
+impl StateDiff {
+    /// 根据执行返回的状态构造差异，`pre_info` 返回账户执行前的信息（代码需要已经载入）
+    pub fn new(state: &EvmState, mut pre_info: impl FnMut(Address) -> AccountInfo) -> StateDiff {
+        let mut diff = StateDiff::default();
+        for (address, account) in state {
+            if !account.is_touched() {
+                continue;
+            }
+            let before = pre_info(*address);
+            let before_code = before.code.as_ref().map(|code| code.original_bytes());
+            let after_code = account.info.code.as_ref().map(|code| code.original_bytes());
+
+            let mut pre = AccountState {
+                balance: Some(before.balance),
+                nonce: (before.nonce != 0).then_some(before.nonce),
+                code: before_code.clone().filter(|code| !code.is_empty()),
+                storage: BTreeMap::new(),
+            };
+            let mut post = AccountState::default();
+            if account.info.balance != before.balance {
+                post.balance = Some(account.info.balance);
+            }
+            if account.info.nonce != before.nonce {
+                post.nonce = Some(account.info.nonce);
+            }
+            if account.info.code_hash != before.code_hash {
+                post.code = after_code;
+            }
+            let mut storage_changed = false;
+            for (slot, value) in &account.storage {
+                if !value.is_changed() {
+                    continue;
+                }
+                storage_changed = true;
+                pre.storage
+                    .insert(B256::from(*slot), B256::from(value.original_value));
+                if !value.present_value.is_zero() {
+                    post.storage
+                        .insert(B256::from(*slot), B256::from(value.present_value));
+                }
+            }
+
+            let modified = post.balance.is_some()
+                || post.nonce.is_some()
+                || post.code.is_some()
+                || storage_changed
+                || account.is_selfdestructed();
+            if !modified {
+                continue;
+            }
+            if !account.is_created() {
+                diff.pre.insert(*address, pre);
+            }
+            if !account.is_selfdestructed() {
+                diff.post.insert(*address, post);
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+
+    /// 发生变化的账户，按地址排序
+    pub fn addresses(&self) -> BTreeSet<Address> {
+        self.pre.keys().chain(self.post.keys()).copied().collect()
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let empty = AccountState::default();
+        for address in self.addresses() {
+            let (pre, post) = match (self.pre.get(&address), self.post.get(&address)) {
+                (Some(pre), Some(post)) => {
+                    writeln!(f, "{address}")?;
+                    (pre, post)
+                }
+                (None, Some(post)) => {
+                    writeln!(f, "{address} (created)")?;
+                    (&empty, post)
+                }
+                (Some(_), None) => {
+                    writeln!(f, "{address} (destroyed)")?;
+                    continue;
+                }
+                (None, None) => unreachable!(),
+            };
+            if let Some(balance) = post.balance {
+                let before = pre.balance.unwrap_or_default();
+                writeln!(f, "  balance: {before} → {balance}")?;
+            }
+            if let Some(nonce) = post.nonce {
+                writeln!(f, "  nonce: {} → {nonce}", pre.nonce.unwrap_or_default())?;
+            }
+            if let Some(code) = &post.code {
+                let before = pre.code.as_ref().map(|code| code.len()).unwrap_or_default();
+                writeln!(f, "  code: {before} bytes → {} bytes", code.len())?;
+            }
+            let slots: BTreeSet<&B256> = pre.storage.keys().chain(post.storage.keys()).collect();
+            for slot in slots {
+                let before = pre.storage.get(slot).copied().unwrap_or_default();
+                let after = post.storage.get(slot).copied().unwrap_or_default();
+                writeln!(
+                    f,
+                    "  storage {slot}: {:#x} → {:#x}",
+                    rU256::from_be_bytes(before.0),
+                    rU256::from_be_bytes(after.0)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 计算执行返回的状态相对当前 `CacheDB` 的差异，应在状态写回之前调用
+    pub fn state_diff(&mut self, state: &EvmState) -> Result<StateDiff, SimulationError> {
+        let mut infos = BTreeMap::new();
+        for address in state.keys() {
+            let mut info = self.load_account(*address)?;
+            if info.code.is_none() {
+                info.code = Some(self.cache_db.code_by_hash(info.code_hash)?);
+            }
+            infos.insert(*address, info);
+        }
+        Ok(StateDiff::new(state, |address| {
+            infos.remove(&address).unwrap_or_default()
+        }))
+    }
+
+    /// 按 `eth_call` 的规则执行交易，返回执行结果和状态差异，状态不会写回 `CacheDB`
+    pub fn simulate_diff(
+        &mut self,
+        tx_env: TxEnv,
+    ) -> Result<(ExecutionResult, StateDiff), SimulationError> {
+        let result = self.simulate(tx_env)?;
+        let diff = self.state_diff(&result.state)?;
+        Ok((result.result, diff))
+    }
+}
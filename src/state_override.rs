@@ -0,0 +1,125 @@
+//! Geth 风格的状态覆盖（`eth_call` 的第三个参数）
+//!
+//! 每个地址可以覆盖余额、nonce、代码，以及存储：`state` 替换整个存储（未列出的槽位视为 0），
+//! `stateDiff` 只修改列出的槽位，两者不能同时设置。JSON 格式与 Geth 相同：
+//!
+//! ```json
+//! { "0x…": { "balance": "0x…", "nonce": "0x1", "code": "0x…", "stateDiff": { "0x…": "0x…" } } }
+//! ```
+
+use ethers_core::types::U64;
+use ethers_providers::JsonRpcClient;
+use revm::primitives::{
+    Address, Bytecode, Bytes, HashMap, ResultAndState, TxEnv, B256, U256 as rU256,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{error::SimulationError, ForkSimulator};
+
+/// 单个账户的覆盖项，未设置的字段保持分叉上的值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<rU256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// 替换账户的全部存储
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<B256, B256>>,
+    /// 只修改列出的存储槽
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<BTreeMap<B256, B256>>,
+}
+
+/// 地址到覆盖项的映射
+pub type StateOverride = BTreeMap<Address, AccountOverride>;
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 把状态覆盖写入 `CacheDB`，之后的所有执行都能看到覆盖后的状态
+    ///
+    /// 先检查全部覆盖项，有不合法的项时不做任何修改。
+    pub fn apply_state_override(
+        &mut self,
+        overrides: &StateOverride,
+    ) -> Result<(), SimulationError> {
+        if let Some(address) = overrides
+            .iter()
+            .find(|(_, account)| account.state.is_some() && account.state_diff.is_some())
+            .map(|(address, _)| address)
+        {
+            return Err(SimulationError::InvalidStateOverride(format!(
+                "账户 {address} 同时设置了 state 和 stateDiff"
+            )));
+        }
+
+        for (address, account) in overrides {
+            let mut info = self.load_account(*address)?;
+            if let Some(balance) = account.balance {
+                info.balance = balance;
+            }
+            if let Some(nonce) = account.nonce {
+                info.nonce = nonce.as_u64();
+            }
+            if let Some(code) = &account.code {
+                let bytecode = Bytecode::new_raw(code.clone());
+                info.code_hash = bytecode.hash_slow();
+                info.code = Some(bytecode);
+            }
+            self.insert_account_info(*address, info);
+
+            if let Some(state) = &account.state {
+                let storage: HashMap<rU256, rU256> = state
+                    .iter()
+                    .map(|(slot, value)| ((*slot).into(), (*value).into()))
+                    .collect();
+                self.replace_account_storage(*address, storage)?;
+            }
+            for (slot, value) in account.state_diff.iter().flatten() {
+                self.insert_account_storage(*address, (*slot).into(), (*value).into())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 在应用了状态覆盖的状态上执行 `f`，结束后撤销覆盖以及 `f` 造成的修改
+    pub fn with_state_override<T>(
+        &mut self,
+        overrides: &StateOverride,
+        f: impl FnOnce(&mut Self) -> Result<T, SimulationError>,
+    ) -> Result<T, SimulationError> {
+        let snapshot = self.snapshot();
+        let result = self.apply_state_override(overrides).and_then(|()| f(self));
+        self.revert_to(snapshot);
+        result
+    }
+
+    /// 带状态覆盖的 [`ForkSimulator::simulate`]，覆盖只在这次执行中生效
+    ///
+    /// 没有覆盖项时直接执行，不创建快照。
+    pub fn simulate_with_override(
+        &mut self,
+        tx_env: TxEnv,
+        overrides: &StateOverride,
+    ) -> Result<ResultAndState, SimulationError> {
+        if overrides.is_empty() {
+            return self.simulate(tx_env);
+        }
+        self.with_state_override(overrides, |simulator| simulator.simulate(tx_env))
+    }
+
+    /// 带状态覆盖的 [`ForkSimulator::estimate_gas`]，没有覆盖项时直接估算
+    pub fn estimate_gas_with_override(
+        &mut self,
+        tx_env: TxEnv,
+        overrides: &StateOverride,
+    ) -> Result<u64, SimulationError> {
+        if overrides.is_empty() {
+            return self.estimate_gas(tx_env);
+        }
+        self.with_state_override(overrides, |simulator| simulator.estimate_gas(tx_env))
+    }
+}
@@ -0,0 +1,233 @@
+//! 操作码级别的追踪，输出 Geth `debug_traceTransaction` 默认追踪器（structLog）的格式
+//!
+//! 输出可以直接与节点返回的追踪逐条比对，也可以载入读取 Geth 追踪格式的调试工具。
+//! 与 Geth 一致：每条记录反映操作码执行前的栈和内存，`gasCost` 对 CALL 类操作码包含转给被调用方的 gas，
+//! `storage` 只出现在 SLOAD/SSTORE 上，内容为当前合约到目前为止读写过的存储槽，
+//! `error` 使用 Geth 的错误描述（如 `out of gas`、`execution reverted`）。
+
+use ethers_providers::JsonRpcClient;
+use revm::{
+    interpreter::{opcode, InstructionResult, Interpreter, OpCode, STACK_LIMIT},
+    primitives::{Address, TxEnv, B256},
+    Database, EvmContext, Inspector,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{error::SimulationError, ForkSimulator};
+
+/// 追踪选项，字段与 Geth 的追踪配置同名
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StructLogConfig {
+    pub disable_stack: bool,
+    pub disable_storage: bool,
+    /// 记录内存，Geth 默认不记录
+    pub enable_memory: bool,
+    /// 记录上一次调用的返回数据
+    pub enable_return_data: bool,
+}
+
+/// 一条操作码记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: String,
+    /// 执行前剩余的 gas
+    pub gas: u64,
+    pub gas_cost: u64,
+    /// 调用深度，顶层调用为 1
+    pub depth: u64,
+    /// 栈，栈底在前，`0x` 开头的紧凑十六进制
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<String>>,
+    /// 内存，按 32 字节一组的十六进制
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_data: Option<String>,
+    /// 存储槽到值的映射，键和值都是 64 位十六进制
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// `debug_traceTransaction` 的返回结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    /// 交易消耗的 gas，包含固有成本
+    pub gas: u64,
+    pub failed: bool,
+    /// 返回数据或回滚数据，不带 `0x` 的十六进制
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// 记录每一步操作码的 inspector
+#[derive(Debug, Default)]
+pub struct StructLogTracer {
+    config: StructLogConfig,
+    logs: Vec<StructLog>,
+    /// 已经记录了执行前状态、等待 `step_end` 补全 gas 消耗的记录及其操作码
+    pending: Option<(StructLog, u8)>,
+    /// 等待 `step_end` 读取结果的 SLOAD 所在合约和槽位
+    pending_sload: Option<(Address, B256)>,
+    /// 各合约读写过的存储槽
+    storage: BTreeMap<Address, BTreeMap<B256, B256>>,
+}
+
+impl StructLogTracer {
+    pub fn new(config: StructLogConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn logs(&self) -> &[StructLog] {
+        &self.logs
+    }
+
+    pub fn into_logs(self) -> Vec<StructLog> {
+        self.logs
+    }
+
+    fn storage_of(&self, address: Address) -> BTreeMap<String, String> {
+        self.storage
+            .get(&address)
+            .into_iter()
+            .flatten()
+            .map(|(slot, value)| (hex::encode(slot), hex::encode(value)))
+            .collect()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StructLogTracer {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let op = interp.current_opcode();
+        let stack = interp.stack().data();
+        let address = interp.contract.target_address;
+        let mut storage = None;
+        if !self.config.disable_storage {
+            match (op, stack.as_slice()) {
+                (opcode::SSTORE, [.., value, slot]) => {
+                    self.storage
+                        .entry(address)
+                        .or_default()
+                        .insert(B256::from(*slot), B256::from(*value));
+                    storage = Some(self.storage_of(address));
+                }
+                (opcode::SLOAD, [.., slot]) => {
+                    self.pending_sload = Some((address, B256::from(*slot)));
+                }
+                _ => {}
+            }
+        }
+        let log = StructLog {
+            pc: interp.program_counter() as u64,
+            op: match OpCode::new(op) {
+                Some(op) => op.as_str().to_owned(),
+                None => format!("opcode {op:#x} not defined"),
+            },
+            gas: interp.gas.remaining(),
+            gas_cost: 0,
+            depth: context.journaled_state.depth(),
+            stack: (!self.config.disable_stack)
+                .then(|| stack.iter().map(|word| format!("{word:#x}")).collect()),
+            memory: self.config.enable_memory.then(|| {
+                interp
+                    .shared_memory
+                    .context_memory()
+                    .chunks(32)
+                    .map(hex::encode)
+                    .collect()
+            }),
+            return_data: (self.config.enable_return_data && !interp.return_data_buffer.is_empty())
+                .then(|| interp.return_data_buffer.to_string()),
+            storage,
+            error: None,
+        };
+        self.pending = Some((log, op));
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Some((mut log, op)) = self.pending.take() else {
+            return;
+        };
+        log.gas_cost = log.gas.saturating_sub(interp.gas.remaining());
+        // SLOAD 读到的值在执行后位于栈顶
+        if let Some((address, slot)) = self.pending_sload.take() {
+            if let (false, Ok(value)) =
+                (interp.instruction_result.is_error(), interp.stack().peek(0))
+            {
+                self.storage
+                    .entry(address)
+                    .or_default()
+                    .insert(slot, B256::from(value));
+                log.storage = Some(self.storage_of(address));
+            }
+        }
+        log.error = geth_error(interp.instruction_result, op, &log.op, interp.stack().len());
+        self.logs.push(log);
+    }
+}
+
+/// 把操作码的执行结果转换为 Geth 的错误描述，执行成功时返回 `None`
+///
+/// 出错时栈保持执行前的状态，`stack_len` 用于拼出与 Geth 相同的栈溢出信息。
+fn geth_error(
+    result: InstructionResult,
+    op: u8,
+    op_name: &str,
+    stack_len: usize,
+) -> Option<String> {
+    use InstructionResult::*;
+    let (inputs, outputs) = OpCode::new(op)
+        .map(|op| (op.inputs() as usize, op.outputs() as usize))
+        .unwrap_or_default();
+    let error = match result {
+        Revert => "execution reverted".to_owned(),
+        _ if !result.is_error() => return None,
+        OutOfGas | MemoryOOG | MemoryLimitOOG | PrecompileOOG | InvalidOperandOOG => {
+            "out of gas".to_owned()
+        }
+        OpcodeNotFound | InvalidFEOpcode | NotActivated => format!("invalid opcode: {op_name}"),
+        CallNotAllowedInsideStatic | StateChangeDuringStaticCall => "write protection".to_owned(),
+        InvalidJump => "invalid jump destination".to_owned(),
+        StackUnderflow => format!("stack underflow ({stack_len} <=> {inputs})"),
+        StackOverflow => format!(
+            "stack limit reached {stack_len} ({})",
+            STACK_LIMIT + inputs - outputs
+        ),
+        OutOfOffset => "return data out of bounds".to_owned(),
+        CreateCollision => "contract address collision".to_owned(),
+        NonceOverflow => "nonce uint64 overflow".to_owned(),
+        CreateContractSizeLimit => "max code size exceeded".to_owned(),
+        CreateContractStartingWithEF => "invalid code: must not begin with 0xef".to_owned(),
+        CreateInitCodeSizeLimit => "max initcode size exceeded".to_owned(),
+        // EOF 等 Geth 没有对应错误的情况，保留 revm 的名称
+        other => format!("{other:?}"),
+    };
+    Some(error)
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 按 `eth_call` 的规则执行交易并记录每一步操作码，状态不会写回 `CacheDB`
+    pub fn trace_struct_logs(
+        &mut self,
+        tx_env: TxEnv,
+        config: StructLogConfig,
+    ) -> Result<StructLogTrace, SimulationError> {
+        let mut tracer = StructLogTracer::new(config);
+        let result = self.inspect(tx_env, &mut tracer)?.result;
+        Ok(StructLogTrace {
+            gas: result.gas_used(),
+            failed: !result.is_success(),
+            return_value: result.output().map(hex::encode).unwrap_or_default(),
+            struct_logs: tracer.into_logs(),
+        })
+    }
+}
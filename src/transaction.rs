@@ -0,0 +1,160 @@
+//! ethers 交易与 revm `TxEnv` 之间的转换
+
+use ethers_core::{
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        NameOrAddress, Transaction, H256, U256,
+    },
+    utils::rlp::Rlp,
+};
+use revm::primitives::{
+    keccak256, AccessListItem, Address, TransactTo, TxEnv, B256, U256 as rU256,
+};
+
+use crate::{
+    error::SimulationError,
+    utils::{to_revm_address, to_revm_b256, to_revm_u256},
+};
+
+/// 已签名交易解码后的结果
+#[derive(Debug, Clone)]
+pub struct DecodedTransaction {
+    pub hash: B256,
+    pub tx_env: TxEnv,
+}
+
+/// 解码 `eth_sendRawTransaction` 格式的已签名交易（legacy、EIP-2930、EIP-1559），并恢复发送者
+pub fn decode_raw_transaction(raw: &[u8]) -> Result<DecodedTransaction, SimulationError> {
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(raw))
+        .map_err(|e| SimulationError::InvalidRawTransaction(e.to_string()))?;
+    let from = signature
+        .recover(tx.sighash())
+        .map_err(|e| SimulationError::InvalidRawTransaction(e.to_string()))?;
+    Ok(DecodedTransaction {
+        hash: keccak256(raw),
+        tx_env: tx_env_from_typed(&tx, to_revm_address(from))?,
+    })
+}
+
+/// 根据交易请求构造 `TxEnv`，`to` 为 ENS 名称时按合约创建处理
+///
+/// gas 上限或 nonce 超出 `u64` 时返回 [`SimulationError::InvalidRawTransaction`]。
+pub fn tx_env_from_typed(tx: &TypedTransaction, from: Address) -> Result<TxEnv, SimulationError> {
+    let transact_to = match tx.to() {
+        Some(NameOrAddress::Address(to)) => TransactTo::Call(to_revm_address(*to)),
+        _ => TransactTo::Create,
+    };
+    let (gas_price, gas_priority_fee) = match tx {
+        TypedTransaction::Eip1559(tx) => (
+            tx.max_fee_per_gas.map(to_revm_u256).unwrap_or_default(),
+            tx.max_priority_fee_per_gas.map(to_revm_u256),
+        ),
+        _ => (tx.gas_price().map(to_revm_u256).unwrap_or_default(), None),
+    };
+    let access_list = tx
+        .access_list()
+        .map(to_revm_access_list)
+        .unwrap_or_default();
+    Ok(TxEnv {
+        caller: from,
+        gas_limit: tx
+            .gas()
+            .map(|gas| to_u64(*gas, "gas"))
+            .transpose()?
+            .unwrap_or(u64::MAX),
+        gas_price,
+        gas_priority_fee,
+        transact_to,
+        value: tx.value().copied().map(to_revm_u256).unwrap_or(rU256::ZERO),
+        data: tx
+            .data()
+            .map(|data| data.0.clone().into())
+            .unwrap_or_default(),
+        nonce: tx
+            .nonce()
+            .map(|nonce| to_u64(*nonce, "nonce"))
+            .transpose()?,
+        // 链 ID 是 `U64`，解码时已经检查过范围
+        chain_id: tx.chain_id().map(|id| id.as_u64()),
+        access_list,
+        ..Default::default()
+    })
+}
+
+/// 根据链上交易构造执行时使用的 `TxEnv`，与出块时的执行参数一致
+///
+/// EIP-1559 及之后的交易使用 `maxFeePerGas`/`maxPriorityFeePerGas`，
+/// 而不是节点返回的实际 `gasPrice`；blob 交易会带上 `blobVersionedHashes` 和 `maxFeePerBlobGas`。
+///
+/// 只支持到 blob 交易（类型 3）为止。EIP-7702 交易（类型 4）的授权列表会改变执行结果，
+/// 当前的 revm 版本又只实现了 Prague 的草案，因此返回
+/// [`SimulationError::UnsupportedTransactionType`] 而不是当作普通调用执行。
+pub fn tx_env_from_transaction(tx: &Transaction) -> Result<TxEnv, SimulationError> {
+    let tx_type = tx
+        .transaction_type
+        .map(|tx_type| tx_type.as_u64())
+        .unwrap_or_default();
+    if tx_type > 3 {
+        return Err(SimulationError::UnsupportedTransactionType(tx_type));
+    }
+    let (gas_price, gas_priority_fee) = match tx.max_fee_per_gas {
+        Some(max_fee) => (
+            to_revm_u256(max_fee),
+            tx.max_priority_fee_per_gas.map(to_revm_u256),
+        ),
+        None => (tx.gas_price.map(to_revm_u256).unwrap_or_default(), None),
+    };
+    let blob_hashes: Vec<H256> = tx
+        .other
+        .get_deserialized("blobVersionedHashes")
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let max_fee_per_blob_gas: Option<U256> = tx
+        .other
+        .get_deserialized("maxFeePerBlobGas")
+        .and_then(Result::ok);
+    Ok(TxEnv {
+        caller: to_revm_address(tx.from),
+        gas_limit: tx.gas.as_u64(),
+        gas_price,
+        gas_priority_fee,
+        transact_to: match tx.to {
+            Some(to) => TransactTo::Call(to_revm_address(to)),
+            None => TransactTo::Create,
+        },
+        value: to_revm_u256(tx.value),
+        data: tx.input.0.clone().into(),
+        nonce: Some(tx.nonce.as_u64()),
+        chain_id: tx.chain_id.map(|id| id.as_u64()),
+        access_list: tx
+            .access_list
+            .as_ref()
+            .map(to_revm_access_list)
+            .unwrap_or_default(),
+        blob_hashes: blob_hashes.into_iter().map(to_revm_b256).collect(),
+        max_fee_per_blob_gas: max_fee_per_blob_gas.map(to_revm_u256),
+        ..Default::default()
+    })
+}
+
+fn to_u64(value: U256, field: &str) -> Result<u64, SimulationError> {
+    u64::try_from(value).map_err(|_| {
+        SimulationError::InvalidRawTransaction(format!("{field} 超出 u64 范围: {value}"))
+    })
+}
+
+fn to_revm_access_list(access_list: &AccessList) -> Vec<AccessListItem> {
+    access_list
+        .0
+        .iter()
+        .map(|item| AccessListItem {
+            address: to_revm_address(item.address),
+            storage_keys: item
+                .storage_keys
+                .iter()
+                .copied()
+                .map(to_revm_b256)
+                .collect(),
+        })
+        .collect()
+}
@@ -0,0 +1,200 @@
+//! 按哈希重放链上的历史交易，并与链上回执比对
+//!
+//! 状态分叉在交易所在区块的父区块上，`BlockEnv` 则使用交易所在区块，与出块时的执行环境一致。
+//! 同一区块中排在前面的交易会改变状态，不重放它们时 gas 消耗和结果都可能与链上不同。
+//! 区块开头的系统调用（如 EIP-4788 信标根）不会执行。
+
+use ethers_core::types::Log as eLog;
+use ethers_providers::{JsonRpcClient, Middleware, Provider};
+use revm::primitives::{ExecutionResult, Log, ResultAndState, TxEnv, B256};
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    error::SimulationError,
+    simulator::{block_env_from, spec_id_from},
+    transaction::tx_env_from_transaction,
+    utils::{to_ethers_h256, to_revm_address, to_revm_b256},
+    ForkOptions, ForkSimulator,
+};
+
+/// 重放历史交易的配置
+#[derive(Debug, Clone, Default)]
+pub struct TxReplayOptions {
+    /// 先依次执行同一区块中排在目标交易之前的交易
+    pub replay_preceding: bool,
+    /// 见 [`ForkOptions::chain_id`]
+    pub chain_id: Option<u64>,
+    /// 见 [`ForkOptions::cache_dir`]，缓存按父区块保存
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// 历史交易的重放结果
+#[derive(Debug, Clone)]
+pub struct TxReplay {
+    pub hash: B256,
+    /// 交易所在区块
+    pub block_number: u64,
+    /// 交易在区块中的位置
+    pub index: u64,
+    /// 重放的前序交易数量
+    pub preceding: usize,
+    pub tx_env: TxEnv,
+    /// 本地执行结果
+    pub result: ExecutionResult,
+    /// 链上回执中的状态、gas 消耗和日志
+    pub expected_success: bool,
+    pub expected_gas_used: u64,
+    pub expected_logs: Vec<Log>,
+}
+
+impl TxReplay {
+    pub fn success(&self) -> bool {
+        self.result.is_success()
+    }
+
+    pub fn gas_used(&self) -> u64 {
+        self.result.gas_used()
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        self.result.logs()
+    }
+
+    /// 本地执行与链上回执不一致的地方，全部一致时为空
+    pub fn mismatches(&self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.success() != self.expected_success {
+            mismatches.push(format!(
+                "状态不一致: 本地 {}，链上 {}",
+                status(self.success()),
+                status(self.expected_success)
+            ));
+        }
+        if self.gas_used() != self.expected_gas_used {
+            mismatches.push(format!(
+                "gas 消耗不一致: 本地 {}，链上 {}",
+                self.gas_used(),
+                self.expected_gas_used
+            ));
+        }
+        if self.logs().len() != self.expected_logs.len() {
+            mismatches.push(format!(
+                "日志数量不一致: 本地 {}，链上 {}",
+                self.logs().len(),
+                self.expected_logs.len()
+            ));
+        } else if let Some(index) = self
+            .logs()
+            .iter()
+            .zip(&self.expected_logs)
+            .position(|(local, expected)| local != expected)
+        {
+            mismatches.push(format!("第 {index} 条日志不一致"));
+        }
+        mismatches
+    }
+
+    /// 状态、gas 消耗和日志都与链上一致
+    pub fn matches(&self) -> bool {
+        self.mismatches().is_empty()
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 按哈希获取链上交易及其区块，在父区块状态上重放，并与链上回执比对
+    ///
+    /// 前序交易和目标交易都按出块时的规则执行（检查 nonce、basefee 和余额），结果写入分叉状态。
+    /// 返回重放后的模拟器，可以在其上继续检查状态或执行其他交易。
+    pub async fn replay_tx(
+        client: Arc<Provider<P>>,
+        hash: B256,
+        options: TxReplayOptions,
+    ) -> Result<(Self, TxReplay), SimulationError> {
+        let tx_hash = to_ethers_h256(hash);
+        let tx = client
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or(SimulationError::TransactionNotFound(hash))?;
+        let (Some(block_number), Some(index)) = (tx.block_number, tx.transaction_index) else {
+            return Err(SimulationError::InvalidArgument(format!(
+                "交易尚未上链: {hash}"
+            )));
+        };
+        let block_number = block_number.as_u64();
+        if block_number == 0 {
+            return Err(SimulationError::InvalidArgument(
+                "无法重放创世区块中的交易".to_string(),
+            ));
+        }
+        let receipt = client
+            .get_transaction_receipt(tx_hash)
+            .await?
+            .ok_or(SimulationError::TransactionNotFound(hash))?;
+        let block = client
+            .get_block_with_txs(block_number)
+            .await?
+            .ok_or_else(|| SimulationError::BlockNotFound(block_number.to_string()))?;
+
+        let mut simulator = Self::new(
+            client,
+            ForkOptions {
+                fork_block: Some((block_number - 1).into()),
+                chain_id: options.chain_id,
+                cache_dir: options.cache_dir,
+            },
+        )
+        .await?;
+        simulator.spec_id = spec_id_from(simulator.chain_id(), &block)?;
+        simulator.block_env = block_env_from(&block);
+
+        let mut preceding = 0;
+        if options.replay_preceding {
+            for tx in block
+                .transactions
+                .iter()
+                .take_while(|block_tx| block_tx.hash != tx_hash)
+            {
+                let executed = tx_env_from_transaction(tx)
+                    .and_then(|tx_env| simulator.transact(tx_env))
+                    .map_err(|source| SimulationError::PrecedingTransaction {
+                        hash: to_revm_b256(tx.hash),
+                        source: Box::new(source),
+                    })?;
+                simulator.commit_state(executed.state);
+                preceding += 1;
+            }
+        }
+
+        let tx_env = tx_env_from_transaction(&tx)?;
+        let ResultAndState { result, state } = simulator.transact(tx_env.clone())?;
+        simulator.commit_state(state);
+        let replay = TxReplay {
+            hash,
+            block_number,
+            index: index.as_u64(),
+            preceding,
+            tx_env,
+            result,
+            expected_success: receipt.status.is_some_and(|status| status.as_u64() == 1),
+            expected_gas_used: receipt.gas_used.unwrap_or_default().as_u64(),
+            expected_logs: receipt.logs.iter().map(to_revm_log).collect(),
+        };
+        Ok((simulator, replay))
+    }
+}
+
+fn to_revm_log(log: &eLog) -> Log {
+    Log::new_unchecked(
+        to_revm_address(log.address),
+        log.topics.iter().copied().map(to_revm_b256).collect(),
+        log.data.0.clone().into(),
+    )
+}
+
+fn status(success: bool) -> &'static str {
+    if success {
+        "成功"
+    } else {
+        "失败"
+    }
+}
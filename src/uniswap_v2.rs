@@ -0,0 +1,336 @@
+//! Uniswap V2 交易对的存储布局
+//!
+//! UniswapV2Pair 继承自 UniswapV2ERC20，槽位 0-4 依次为 totalSupply、balanceOf、allowance、
+//! DOMAIN_SEPARATOR 和 nonces，交易对自身的状态从槽位 5 开始。
+
+use ethers_contract::BaseContract;
+use ethers_core::{abi::parse_abi, types::Bytes as eBytes};
+use ethers_providers::JsonRpcClient;
+use revm::primitives::{Address, TransactTo, TxEnv, U256 as rU256};
+
+use crate::{
+    erc20::MappingSlot,
+    error::{ensure_success, SimulationError},
+    events::{DecodedLog, KnownEvent, LogDecoder},
+    utils::{to_ethers_address, to_ethers_u256},
+    ForkSimulator,
+};
+
+pub const FACTORY_SLOT: u64 = 5;
+pub const TOKEN0_SLOT: u64 = 6;
+pub const TOKEN1_SLOT: u64 = 7;
+/// reserve0、reserve1 和 blockTimestampLast 打包存放在同一个槽位
+pub const RESERVES_SLOT: u64 = 8;
+pub const PRICE0_CUMULATIVE_LAST_SLOT: u64 = 9;
+pub const PRICE1_CUMULATIVE_LAST_SLOT: u64 = 10;
+pub const K_LAST_SLOT: u64 = 11;
+
+const RESERVE_BITS: usize = 112;
+
+/// 按交易对合约的公式（0.3% 手续费）计算输出数量，与 UniswapV2Library.getAmountOut 一致
+///
+/// 中间结果超出 uint256 时返回 `None`，对应合约中 SafeMath 的回滚。
+pub fn get_amount_out(amount_in: rU256, reserve_in: rU256, reserve_out: rU256) -> Option<rU256> {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return Some(rU256::ZERO);
+    }
+    let amount_in_with_fee = amount_in.checked_mul(rU256::from(997))?;
+    let numerator = amount_in_with_fee.checked_mul(reserve_out)?;
+    let denominator = reserve_in
+        .checked_mul(rU256::from(1000))?
+        .checked_add(amount_in_with_fee)?;
+    Some(numerator / denominator)
+}
+
+/// 得到 `amount_out` 所需的最少输入数量，与 UniswapV2Library.getAmountIn 一致
+///
+/// 储备量不足或中间结果超出 uint256 时返回 `None`。
+pub fn get_amount_in(amount_out: rU256, reserve_in: rU256, reserve_out: rU256) -> Option<rU256> {
+    if amount_out.is_zero() || reserve_in.is_zero() || amount_out >= reserve_out {
+        return None;
+    }
+    let numerator = reserve_in
+        .checked_mul(amount_out)?
+        .checked_mul(rU256::from(1000))?;
+    let denominator = (reserve_out - amount_out).checked_mul(rU256::from(997))?;
+    (numerator / denominator).checked_add(rU256::from(1))
+}
+
+/// 槽位 8 中打包的储备量
+///
+/// 从低位到高位依次为 reserve0（112 位）、reserve1（112 位）、blockTimestampLast（32 位）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PairReserves {
+    pub reserve0: u128,
+    pub reserve1: u128,
+    pub block_timestamp_last: u32,
+}
+
+impl PairReserves {
+    /// 从槽位 8 的原始值解码
+    pub fn from_slot(value: rU256) -> Self {
+        let mask = (rU256::from(1) << RESERVE_BITS) - rU256::from(1);
+        Self {
+            reserve0: (value & mask).to(),
+            reserve1: ((value >> RESERVE_BITS) & mask).to(),
+            block_timestamp_last: (value >> (2 * RESERVE_BITS)).to(),
+        }
+    }
+
+    /// 编码为槽位 8 的原始值，储备量超过 uint112 时返回错误
+    pub fn to_slot(&self) -> Result<rU256, SimulationError> {
+        if self.reserve0 >> RESERVE_BITS != 0 || self.reserve1 >> RESERVE_BITS != 0 {
+            return Err(SimulationError::InvalidArgument(format!(
+                "储备量超出 uint112 范围: {self:?}"
+            )));
+        }
+        Ok(rU256::from(self.reserve0)
+            | rU256::from(self.reserve1) << RESERVE_BITS
+            | rU256::from(self.block_timestamp_last) << (2 * RESERVE_BITS))
+    }
+}
+
+/// UniswapV2Pair 的完整状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UniswapV2PairState {
+    pub factory: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub reserves: PairReserves,
+    pub price0_cumulative_last: rU256,
+    pub price1_cumulative_last: rU256,
+    pub k_last: rU256,
+}
+
+impl UniswapV2PairState {
+    /// 通过读取存储槽的函数解码，`read` 的参数为槽位索引
+    pub fn decode<E>(mut read: impl FnMut(rU256) -> Result<rU256, E>) -> Result<Self, E> {
+        let mut slot = |index: u64| read(rU256::from(index));
+        Ok(Self {
+            factory: slot_to_address(slot(FACTORY_SLOT)?),
+            token0: slot_to_address(slot(TOKEN0_SLOT)?),
+            token1: slot_to_address(slot(TOKEN1_SLOT)?),
+            reserves: PairReserves::from_slot(slot(RESERVES_SLOT)?),
+            price0_cumulative_last: slot(PRICE0_CUMULATIVE_LAST_SLOT)?,
+            price1_cumulative_last: slot(PRICE1_CUMULATIVE_LAST_SLOT)?,
+            k_last: slot(K_LAST_SLOT)?,
+        })
+    }
+
+    /// 编码为 `(槽位, 值)` 列表
+    pub fn encode(&self) -> Result<Vec<(rU256, rU256)>, SimulationError> {
+        Ok([
+            (FACTORY_SLOT, address_to_slot(self.factory)),
+            (TOKEN0_SLOT, address_to_slot(self.token0)),
+            (TOKEN1_SLOT, address_to_slot(self.token1)),
+            (RESERVES_SLOT, self.reserves.to_slot()?),
+            (PRICE0_CUMULATIVE_LAST_SLOT, self.price0_cumulative_last),
+            (PRICE1_CUMULATIVE_LAST_SLOT, self.price1_cumulative_last),
+            (K_LAST_SLOT, self.k_last),
+        ]
+        .into_iter()
+        .map(|(slot, value)| (rU256::from(slot), value))
+        .collect())
+    }
+}
+
+fn slot_to_address(value: rU256) -> Address {
+    Address::from_word(value.into())
+}
+
+fn address_to_slot(address: Address) -> rU256 {
+    address.into_word().into()
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 直接从存储读取交易对状态，不经过 EVM
+    pub fn pair_state(&mut self, pair: Address) -> Result<UniswapV2PairState, SimulationError> {
+        UniswapV2PairState::decode(|slot| self.load_storage(pair, slot))
+    }
+
+    /// 读取交易对的储备量
+    pub fn pair_reserves(&mut self, pair: Address) -> Result<PairReserves, SimulationError> {
+        let value = self.load_storage(pair, rU256::from(RESERVES_SLOT))?;
+        Ok(PairReserves::from_slot(value))
+    }
+
+    /// 覆盖交易对的储备量，之后的模拟都会看到新的值
+    pub fn set_pair_reserves(
+        &mut self,
+        pair: Address,
+        reserves: PairReserves,
+    ) -> Result<(), SimulationError> {
+        self.insert_account_storage(pair, rU256::from(RESERVES_SLOT), reserves.to_slot()?)
+    }
+
+    /// 覆盖交易对的全部状态槽位
+    pub fn set_pair_state(
+        &mut self,
+        pair: Address,
+        state: &UniswapV2PairState,
+    ) -> Result<(), SimulationError> {
+        for (slot, value) in state.encode()? {
+            self.insert_account_storage(pair, slot, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// 直接对交易对调用 `swap` 的参数
+#[derive(Debug, Clone)]
+pub struct V2Swap {
+    pub pair: Address,
+    /// 输入代币，必须是交易对的 token0 或 token1
+    pub token_in: Address,
+    pub amount_in: rU256,
+    /// `token_in` 合约中 `balanceOf` 映射的槽位，用来给交易对注入输入代币，
+    /// `None` 时通过 [`ForkSimulator::find_balance_slot`] 自动发现
+    pub balance_slot: Option<MappingSlot>,
+    /// 请求的输出数量，`None` 时按当前储备量用 [`get_amount_out`] 计算
+    pub amount_out: Option<rU256>,
+    /// 输出代币的接收地址，同时作为交易的发送者
+    pub to: Address,
+}
+
+/// 一次 swap 模拟的结果，输入输出数量取自交易对发出的 `Swap` 事件
+#[derive(Debug, Clone)]
+pub struct V2SwapOutcome {
+    pub amount0_in: rU256,
+    pub amount1_in: rU256,
+    pub amount0_out: rU256,
+    pub amount1_out: rU256,
+    /// 链下按 [`get_amount_out`] 计算的输出数量
+    pub expected_amount_out: rU256,
+    pub gas_used: u64,
+    pub reserves_before: PairReserves,
+    pub reserves_after: PairReserves,
+    /// 执行中发出的、能够识别的事件，包括代币的 `Transfer` 和交易对的 `Sync`、`Swap`
+    pub logs: Vec<DecodedLog>,
+}
+
+impl V2SwapOutcome {
+    /// 实际得到的输出代币数量
+    pub fn amount_out(&self) -> rU256 {
+        self.amount0_out.max(self.amount1_out)
+    }
+}
+
+impl<P: JsonRpcClient + 'static> ForkSimulator<P> {
+    /// 在分叉状态上模拟一次交易对的 `swap`
+    ///
+    /// 先通过存储覆盖把 `amount_in` 个输入代币记到交易对名下（相当于 Router 先转账），
+    /// 再调用 `swap(amount0Out, amount1Out, to, "")`。模拟结束后恢复交易对的代币余额，
+    /// 执行产生的状态变化不会写回 `CacheDB`。
+    pub fn simulate_v2_swap(&mut self, swap: &V2Swap) -> Result<V2SwapOutcome, SimulationError> {
+        let state = self.pair_state(swap.pair)?;
+        let reserves_before = state.reserves;
+        let (reserve0, reserve1) = (
+            rU256::from(reserves_before.reserve0),
+            rU256::from(reserves_before.reserve1),
+        );
+        let zero_for_one = if swap.token_in == state.token0 {
+            true
+        } else if swap.token_in == state.token1 {
+            false
+        } else {
+            return Err(SimulationError::InvalidArgument(format!(
+                "{} 不是交易对 {} 的代币",
+                swap.token_in, swap.pair
+            )));
+        };
+        let (reserve_in, reserve_out) = if zero_for_one {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        };
+        let expected_amount_out = get_amount_out(swap.amount_in, reserve_in, reserve_out)
+            .ok_or_else(|| {
+                SimulationError::InvalidArgument(format!(
+                    "输入数量 {} 导致 getAmountOut 溢出",
+                    swap.amount_in
+                ))
+            })?;
+        let amount_out = swap.amount_out.unwrap_or(expected_amount_out);
+        let (amount0_out, amount1_out) = if zero_for_one {
+            (rU256::ZERO, amount_out)
+        } else {
+            (amount_out, rU256::ZERO)
+        };
+
+        let pair_contract = BaseContract::from(
+            parse_abi(&[
+                "function swap(uint amount0Out, uint amount1Out, address to, bytes data) external",
+            ])
+            .expect("交易对 ABI 声明有效"),
+        );
+        let calldata = pair_contract.encode(
+            "swap",
+            (
+                to_ethers_u256(amount0_out),
+                to_ethers_u256(amount1_out),
+                to_ethers_address(swap.to),
+                eBytes::new(),
+            ),
+        )?;
+
+        // 注入输入代币，执行后无论成功与否都恢复原余额
+        let balance_slot = match swap.balance_slot {
+            Some(mapping) => mapping,
+            None => self.find_balance_slot(swap.token_in)?,
+        }
+        .slot(swap.pair);
+        let balance = self.load_storage(swap.token_in, balance_slot)?;
+        let injected = balance.checked_add(swap.amount_in).ok_or_else(|| {
+            SimulationError::InvalidArgument(format!(
+                "交易对余额 {balance} 加上输入数量 {} 超出 uint256",
+                swap.amount_in
+            ))
+        })?;
+        self.insert_account_storage(swap.token_in, balance_slot, injected)?;
+        let executed = self.simulate(TxEnv {
+            caller: swap.to,
+            transact_to: TransactTo::Call(swap.pair),
+            data: calldata.0.into(),
+            ..Default::default()
+        });
+        self.insert_account_storage(swap.token_in, balance_slot, balance)?;
+        let ref_tx = executed?;
+
+        let (_, gas_used, logs) = ensure_success(ref_tx.result)?;
+        let logs = LogDecoder::new().decode_logs(&logs);
+        let (amount0_in, amount1_in, amount0_out, amount1_out) = logs
+            .iter()
+            .filter(|log| log.address == swap.pair)
+            .find_map(|log| match log.event()? {
+                KnownEvent::Swap {
+                    amount0_in,
+                    amount1_in,
+                    amount0_out,
+                    amount1_out,
+                    ..
+                } => Some((amount0_in, amount1_in, amount0_out, amount1_out)),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                SimulationError::UnexpectedResult("swap 成功但没有找到 Swap 事件".to_string())
+            })?;
+        let reserves_after = ref_tx
+            .state
+            .get(&swap.pair)
+            .and_then(|account| account.storage.get(&rU256::from(RESERVES_SLOT)))
+            .map(|slot| PairReserves::from_slot(slot.present_value()))
+            .unwrap_or(reserves_before);
+
+        Ok(V2SwapOutcome {
+            amount0_in,
+            amount1_in,
+            amount0_out,
+            amount1_out,
+            expected_amount_out,
+            gas_used,
+            reserves_before,
+            reserves_after,
+            logs,
+        })
+    }
+}
@@ -0,0 +1,70 @@
+//! ethers 与 revm 基础类型之间的转换，以及存储槽计算
+
+use ethers_core::types::{
+    transaction::eip2718::TypedTransaction, TransactionRequest, H160, H256, U256,
+};
+use revm::primitives::{keccak256, Address, TransactTo, TxEnv, B256, U256 as rU256};
+
+pub fn to_revm_u256(value: U256) -> rU256 {
+    rU256::from_limbs(value.0)
+}
+
+pub fn to_ethers_u256(value: rU256) -> U256 {
+    U256(value.into_limbs())
+}
+
+pub fn to_revm_address(address: H160) -> Address {
+    Address::from(address.0)
+}
+
+pub fn to_ethers_address(address: Address) -> H160 {
+    H160(address.into_array())
+}
+
+pub fn to_revm_b256(hash: H256) -> B256 {
+    B256::from(hash.0)
+}
+
+pub fn to_ethers_h256(hash: B256) -> H256 {
+    H256(hash.0)
+}
+
+/// Solidity `mapping(address => ...)` 中 `key` 对应的存储槽：`keccak256(abi.encode(key, slot))`
+pub fn mapping_slot(key: Address, slot: rU256) -> rU256 {
+    let mut buf = [0u8; 64];
+    buf[12..32].copy_from_slice(key.as_slice());
+    buf[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+    keccak256(buf).into()
+}
+
+/// 两层映射 `mapping(address => mapping(address => ...))` 中 `(outer, inner)` 对应的存储槽
+pub fn nested_mapping_slot(outer: Address, inner: Address, slot: rU256) -> rU256 {
+    mapping_slot(inner, mapping_slot(outer, slot))
+}
+
+/// revm 地址列表转换为 ethers 地址列表
+pub fn to_ethers_addresses(addresses: &[Address]) -> Vec<H160> {
+    addresses.iter().copied().map(to_ethers_address).collect()
+}
+
+/// 把 `TxEnv` 转换为发给节点的交易请求，用于 `eth_createAccessList` 等 RPC
+///
+/// gas 价格为 0 时不设置，由节点按 `eth_call` 的规则处理。
+pub fn to_typed_transaction(tx_env: &TxEnv, chain_id: u64) -> TypedTransaction {
+    let mut request = TransactionRequest::new()
+        .from(to_ethers_address(tx_env.caller))
+        .data(tx_env.data.to_vec())
+        .value(to_ethers_u256(tx_env.value))
+        .gas(tx_env.gas_limit)
+        .chain_id(chain_id);
+    if let TransactTo::Call(to) = tx_env.transact_to {
+        request = request.to(to_ethers_address(to));
+    }
+    if let Some(nonce) = tx_env.nonce {
+        request = request.nonce(nonce);
+    }
+    if !tx_env.gas_price.is_zero() {
+        request = request.gas_price(to_ethers_u256(tx_env.gas_price));
+    }
+    request.into()
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::*;
+use revm::primitives::{AccessListItem, Address, Bytes, TransactTo, TxEnv, B256};
+use revm_example::utils::to_typed_transaction;
+use serde_json::json;
+
+fn get_reserves_tx() -> TxEnv {
+    TxEnv {
+        caller: Address::ZERO,
+        transact_to: TransactTo::Call(pool_address()),
+        data: Bytes::from(pair_contract().encode("getReserves", ()).unwrap().0),
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn traced_access_list_prefetches_fork() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let access_list = simulator.trace_access_list(get_reserves_tx()).unwrap();
+    let pool = access_list
+        .0
+        .iter()
+        .find(|item| item.address == pool_address())
+        .unwrap();
+    assert_eq!(pool.storage_keys, [B256::with_last_byte(8)]);
+    assert!(access_list
+        .0
+        .iter()
+        .any(|item| item.address == Address::ZERO));
+
+    // 在新的分叉上先按访问列表预取，执行时不再访问节点
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    simulator.prefetch_access_list(&access_list).await.unwrap();
+    simulator.simulate(get_reserves_tx()).unwrap();
+    assert_eq!(simulator.cache_db().db.network_requests(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prefetch_from_create_access_list() {
+    let client = replay_client();
+    let mut simulator = replay_simulator(client.clone(), fork_options()).await;
+    let mut tx = get_reserves_tx();
+    tx.gas_limit = tx
+        .gas_limit
+        .min(simulator.block_env().gas_limit.saturating_to());
+    client.insert(
+        "eth_createAccessList",
+        json!([
+            serde_json::to_value(to_typed_transaction(&tx, 1)).unwrap(),
+            format!("{FORK_BLOCK:#x}"),
+        ]),
+        json!({
+            "accessList": [{
+                "address": pool_address(),
+                "storageKeys": [B256::with_last_byte(8)],
+            }],
+            "gasUsed": "0x5208",
+        }),
+    );
+
+    let access_list = simulator.create_access_list(&tx).await.unwrap();
+    assert_eq!(
+        access_list.0,
+        [AccessListItem {
+            address: pool_address(),
+            storage_keys: vec![B256::with_last_byte(8)],
+        }]
+    );
+    let record = simulator.prefetch_for(&tx).await.unwrap();
+    assert_eq!(record.storage_len(), 1);
+    simulator.simulate(tx).unwrap();
+    assert_eq!(simulator.cache_db().db.network_requests(), 0);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::*;
+use ethers_core::{
+    k256::ecdsa::SigningKey,
+    types::{transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, U256},
+    utils::secret_key_to_address,
+};
+use revm::primitives::{
+    address, AccountInfo, Address, Bytes, InvalidTransaction, TransactTo, TxEnv, I256,
+    U256 as rU256,
+};
+use revm_example::{
+    utils::{to_ethers_address, to_revm_address, to_revm_u256},
+    BundleTx, RevertReason, SimulationError,
+};
+
+const SEARCHER: Address = address!("5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e");
+
+/// 用测试私钥签名的 EIP-1559 转账，优先费为 1 gwei（不超过 `max_fee_per_gas`）
+fn signed_transfer(key: &SigningKey, to: Address, value: rU256, max_fee_per_gas: rU256) -> Bytes {
+    let max_fee_per_gas = U256(max_fee_per_gas.into_limbs());
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(to_ethers_address(to))
+        .value(U256(value.into_limbs()))
+        .gas(21_000)
+        .max_fee_per_gas(max_fee_per_gas)
+        .max_priority_fee_per_gas(max_fee_per_gas.min(1_000_000_000u64.into()))
+        .nonce(0)
+        .chain_id(1)
+        .into();
+    sign_transaction(key, tx).0.into()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn bundle_reports_per_tx_results_and_profit() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    insert_contract(&mut simulator, REVERTER, Bytes::from_static(&REVERTER_CODE));
+    let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+    let signer = to_revm_address(secret_key_to_address(&key));
+    for account in [signer, SEARCHER] {
+        simulator.cache_db_mut().insert_account_info(
+            account,
+            AccountInfo {
+                balance: ether(10),
+                ..Default::default()
+            },
+        );
+    }
+
+    let basefee = simulator.block_env().basefee;
+    let call = |to| TxEnv {
+        caller: SEARCHER,
+        transact_to: TransactTo::Call(to),
+        gas_limit: 100_000,
+        ..Default::default()
+    };
+    let txs: Vec<BundleTx> = vec![
+        signed_transfer(&key, SEARCHER, ether(1), basefee * rU256::from(2)).into(),
+        call(COUNTER).into(),
+        call(REVERTER).into(),
+    ];
+    let result = simulator.simulate_bundle(&txs, SEARCHER, &[]).unwrap();
+
+    assert_eq!(result.txs.len(), 3);
+    assert_eq!(result.txs[0].from, signer);
+    assert!(result.txs[0].hash.is_some());
+    assert_eq!(result.txs[0].gas_used, 21_000);
+    assert!(result.txs[1].success);
+    assert_eq!(result.txs[1].logs.len(), 1);
+    assert!(!result.txs[2].success);
+    assert_eq!(result.txs[2].revert_reason, Some(RevertReason::Empty));
+    assert!(!result.all_succeeded());
+    assert_eq!(
+        result.gas_used,
+        result.txs.iter().map(|tx| tx.gas_used).sum::<u64>()
+    );
+    // 搜索者收到 1 ETH，自己的两笔交易 gas 价格为 0
+    assert_eq!(result.eth_delta, I256::from_raw(ether(1)));
+    // 验证者只收到签名交易的优先费
+    assert_eq!(
+        result.coinbase_delta,
+        I256::from_raw(to_revm_u256(U256::from(1_000_000_000u64)) * rU256::from(21_000))
+    );
+
+    // 交易包执行后分叉状态恢复
+    assert!(simulator.receipts().is_empty());
+    assert_eq!(
+        simulator.cache_db().accounts[&SEARCHER].info.balance,
+        ether(10)
+    );
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::ZERO).unwrap(),
+        rU256::ZERO
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signed_transactions_are_fully_validated() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+    let signer = to_revm_address(secret_key_to_address(&key));
+    simulator.insert_account_info(signer, AccountInfo::from_balance(ether(10)));
+    simulator.insert_account_info(SEARCHER, AccountInfo::default());
+    let basefee = simulator.block_env().basefee;
+
+    // 低于 basefee 的交易不能上链，未签名交易则按 eth_call 的规则不检查
+    let underpriced = signed_transfer(&key, SEARCHER, ether(1), basefee - rU256::from(1));
+    assert!(matches!(
+        simulator.simulate_bundle(&[underpriced.into()], SEARCHER, &[]),
+        Err(SimulationError::InvalidTransaction(
+            InvalidTransaction::GasPriceLessThanBasefee
+        ))
+    ));
+    let unsigned = TxEnv {
+        caller: signer,
+        transact_to: TransactTo::Call(SEARCHER),
+        value: ether(1),
+        gas_price: basefee - rU256::from(1),
+        ..Default::default()
+    };
+    assert!(simulator
+        .simulate_bundle(&[unsigned.into()], SEARCHER, &[])
+        .unwrap()
+        .all_succeeded());
+
+    // 余额不足以支付 gas 上限时同样无效
+    let poor = signed_transfer(&key, SEARCHER, ether(10), basefee * rU256::from(2));
+    assert!(matches!(
+        simulator.simulate_bundle(&[poor.into()], SEARCHER, &[]),
+        Err(SimulationError::InvalidTransaction(
+            InvalidTransaction::LackOfFundForMaxFee { .. }
+        ))
+    ));
+    assert_eq!(simulator.load_account(signer).unwrap().balance, ether(10));
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::*;
+use hex_literal::hex;
+use revm::primitives::{address, AccountInfo, Address, Bytes, TransactTo, TxEnv};
+use revm_example::CallKind;
+
+const OUTER: Address = address!("00000000000000000000000000000000000c0de0");
+const DEPLOYER: Address = address!("00000000000000000000000000000000000de910");
+
+/// 以 `Error("nope")` 回滚：把代码末尾的 100 字节回滚数据复制到内存后 REVERT
+fn reverter_code() -> Bytes {
+    let data = concat!(
+        "08c379a0",
+        "0000000000000000000000000000000000000000000000000000000000000020",
+        "0000000000000000000000000000000000000000000000000000000000000004",
+        "6e6f706500000000000000000000000000000000000000000000000000000000",
+    );
+    hex::decode(format!("6064600c60003960646000fd{data}"))
+        .unwrap()
+        .into()
+}
+
+/// 依次 CALL 计数器、STATICCALL 计数器（写存储，异常终止）、CALL 回滚合约，最后 STOP
+fn outer_code() -> Bytes {
+    let call = |target: Address, opcode: &str, with_value: bool| {
+        format!(
+            "6000600060006000{}73{}5a{opcode}50",
+            if with_value { "6000" } else { "" },
+            hex::encode(target)
+        )
+    };
+    let code = [
+        call(COUNTER, "f1", true),
+        call(COUNTER, "fa", false),
+        call(REVERTER, "f1", true),
+        "00".to_owned(),
+    ]
+    .concat();
+    hex::decode(code).unwrap().into()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn call_tracer_builds_nested_tree() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, OUTER, outer_code());
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    insert_contract(&mut simulator, REVERTER, reverter_code());
+
+    let root = simulator
+        .trace_calls(TxEnv {
+            transact_to: TransactTo::Call(OUTER),
+            data: Bytes::from_static(&hex!("deadbeef")),
+            ..Default::default()
+        })
+        .unwrap();
+
+    assert_eq!(root.kind, CallKind::Call);
+    assert_eq!(root.to, Some(OUTER));
+    assert_eq!(root.depth, 0);
+    assert!(root.success);
+    assert_eq!(root.calls.len(), 3);
+
+    let [counter, static_counter, reverter] = &root.calls[..] else {
+        unreachable!()
+    };
+    assert_eq!(counter.from, OUTER);
+    assert_eq!(counter.to, Some(COUNTER));
+    assert_eq!(counter.depth, 1);
+    assert!(counter.success);
+    assert_eq!(counter.output.len(), 32);
+    assert_eq!(static_counter.kind, CallKind::StaticCall);
+    assert!(!static_counter.success);
+    assert_eq!(static_counter.status, "StateChangeDuringStaticCall");
+    assert_eq!(reverter.status, "Revert");
+    assert!(reverter.revert_reason.as_deref().unwrap().contains("nope"));
+    assert!(reverter.gas_used > 0 && reverter.gas_used < reverter.gas);
+
+    let tree = root.to_string();
+    let lines: Vec<&str> = tree.lines().collect();
+    assert_eq!(lines.len(), 8, "{tree}");
+    assert!(lines[0].ends_with(&format!("{OUTER}::0xdeadbeef()")));
+    assert!(lines[1].starts_with("├─ [") && lines[1].ends_with(&format!("{COUNTER}::fallback()")));
+    assert_eq!(lines[2], format!("│   └─ ← [Return] {}", counter.output));
+    assert!(lines[3].ends_with("[staticcall]"));
+    assert_eq!(lines[4], "│   └─ ← [StateChangeDuringStaticCall]");
+    assert!(lines[6].starts_with("│   └─ ← [Revert] ") && lines[6].contains("nope"));
+    assert_eq!(lines[7], "└─ ← [Stop]");
+
+    let json = serde_json::to_value(&root).unwrap();
+    assert_eq!(json["type"], "CALL");
+    assert_eq!(json["calls"][1]["type"], "STATICCALL");
+    assert_eq!(json["calls"][2]["success"], false);
+    assert_eq!(json["calls"][0]["calls"], serde_json::json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn call_tracer_records_contract_creation() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    // 初始化代码把后面的 24 字节计数器代码复制到内存并返回
+    let init_code = [&hex!("6018600c60003960186000f3")[..], &COUNTER_CODE].concat();
+    // 部署者和新合约地址都放在本地，不请求节点
+    let db = simulator.cache_db_mut();
+    db.insert_account_info(DEPLOYER, AccountInfo::default());
+    db.insert_account_info(DEPLOYER.create(0), AccountInfo::default());
+
+    let root = simulator
+        .trace_calls(TxEnv {
+            caller: DEPLOYER,
+            transact_to: TransactTo::Create,
+            data: init_code.clone().into(),
+            ..Default::default()
+        })
+        .unwrap();
+
+    assert_eq!(root.kind, CallKind::Create);
+    assert!(root.success);
+    assert_eq!(root.from, DEPLOYER);
+    assert_eq!(root.to, Some(DEPLOYER.create(0)));
+    assert_eq!(root.input, Bytes::from(init_code));
+    assert_eq!(root.output, Bytes::from_static(&COUNTER_CODE));
+    assert!(root
+        .to_string()
+        .ends_with("└─ ← [Return] 24 bytes of code\n"));
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::*;
+use revm::primitives::{AccountInfo, Bytes, InvalidTransaction, TransactTo, TxEnv, U256 as rU256};
+use revm_example::{replay::ReplayClient, ForkSimulator, SimulationError};
+
+async fn counter_simulator() -> ForkSimulator<ReplayClient> {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    simulator.cache_db_mut().insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: rU256::from(10).pow(rU256::from(18)),
+            ..Default::default()
+        },
+    );
+    simulator
+}
+
+fn increment(gas_price: rU256) -> TxEnv {
+    TxEnv {
+        caller: SENDER,
+        transact_to: TransactTo::Call(COUNTER),
+        gas_limit: 100_000,
+        gas_price,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn committed_transactions_see_previous_effects() {
+    let mut simulator = counter_simulator().await;
+    let gas_price = rU256::from(1_000_000_000u64);
+    let receipts = simulator
+        .commit_all((0..3).map(|_| increment(gas_price)))
+        .unwrap();
+
+    for (i, receipt) in receipts.iter().enumerate() {
+        assert!(receipt.success());
+        assert_eq!(receipt.index, i);
+        assert_eq!(receipt.nonce, i as u64);
+        assert_eq!(
+            receipt.output(),
+            Bytes::from(rU256::from(i + 1).to_be_bytes_vec())
+        );
+        assert_eq!(receipt.logs().len(), 1);
+    }
+    let total_gas: u64 = receipts.iter().map(|r| r.gas_used).sum();
+    assert_eq!(receipts[2].cumulative_gas_used, total_gas);
+
+    let sender = simulator.cache_db().accounts[&SENDER].info.clone();
+    assert_eq!(sender.nonce, 3);
+    assert_eq!(
+        sender.balance,
+        rU256::from(10).pow(rU256::from(18)) - gas_price * rU256::from(total_gas)
+    );
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::ZERO).unwrap(),
+        rU256::from(3)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_transaction_is_not_committed() {
+    let mut simulator = counter_simulator().await;
+    simulator.commit(increment(rU256::ZERO)).unwrap();
+    let snapshot = simulator.snapshot();
+    simulator.commit(increment(rU256::ZERO)).unwrap();
+
+    let error = simulator
+        .commit(TxEnv {
+            nonce: Some(0),
+            ..increment(rU256::ZERO)
+        })
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SimulationError::InvalidTransaction(InvalidTransaction::NonceTooLow { .. })
+    ));
+    assert_eq!(simulator.receipts().len(), 2);
+
+    // 回滚会同时撤销状态和回执
+    assert!(simulator.revert_to(snapshot));
+    assert_eq!(simulator.receipts().len(), 1);
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::ZERO).unwrap(),
+        rU256::from(1)
+    );
+}
@@ -0,0 +1,91 @@
+//! 测试合约使用的简易 EVM 汇编
+//!
+//! 源码按空白分隔：助记符与 revm 的操作码名称一致（如 `KECCAK256`）；数字（十进制或 `0x` 十六进制）
+//! 按最短的 PUSH 压栈；`name:` 定义跳转目标并插入 JUMPDEST；`@name` 以 PUSH2 压入跳转目标的地址；
+//! `;` 之后到行尾为注释。
+
+use revm::{interpreter::OpCode, primitives::Bytes};
+use std::collections::HashMap;
+
+enum Item {
+    Op(u8),
+    Push(Vec<u8>),
+    Label(String),
+    LabelRef(String),
+}
+
+pub fn assemble(source: &str) -> Bytes {
+    let opcodes: HashMap<&str, u8> = (0..=u8::MAX)
+        .filter_map(|byte| OpCode::new(byte).map(|op| (op.as_str(), byte)))
+        .collect();
+    let items: Vec<Item> = source
+        .lines()
+        .flat_map(|line| line.split(';').next().unwrap().split_whitespace())
+        .map(|token| {
+            if let Some(label) = token.strip_suffix(':') {
+                Item::Label(label.to_string())
+            } else if let Some(label) = token.strip_prefix('@') {
+                Item::LabelRef(label.to_string())
+            } else if let Some(hex) = token.strip_prefix("0x") {
+                let hex = if hex.len() % 2 == 1 {
+                    format!("0{hex}")
+                } else {
+                    hex.to_string()
+                };
+                Item::Push(trim(hex::decode(hex).unwrap()))
+            } else if let Ok(value) = token.parse::<u128>() {
+                Item::Push(trim(value.to_be_bytes().to_vec()))
+            } else {
+                Item::Op(
+                    *opcodes
+                        .get(token)
+                        .unwrap_or_else(|| panic!("未知指令 {token}")),
+                )
+            }
+        })
+        .collect();
+
+    let mut labels = HashMap::new();
+    let mut offset = 0;
+    for item in &items {
+        match item {
+            Item::Op(_) => offset += 1,
+            Item::Push(bytes) => offset += 1 + bytes.len(),
+            Item::Label(label) => {
+                labels.insert(label.as_str(), offset);
+                offset += 1;
+            }
+            Item::LabelRef(_) => offset += 3,
+        }
+    }
+
+    let mut code = Vec::with_capacity(offset);
+    for item in &items {
+        match item {
+            Item::Op(byte) => code.push(*byte),
+            Item::Push(bytes) => {
+                assert!(bytes.len() <= 32, "立即数超过 32 字节");
+                code.push(0x5f + bytes.len() as u8);
+                code.extend_from_slice(bytes);
+            }
+            Item::Label(_) => code.push(0x5b),
+            Item::LabelRef(label) => {
+                let target = *labels
+                    .get(label.as_str())
+                    .unwrap_or_else(|| panic!("未定义的标签 {label}"));
+                code.push(0x61);
+                code.extend_from_slice(&u16::try_from(target).unwrap().to_be_bytes());
+            }
+        }
+    }
+    code.into()
+}
+
+/// 去掉前导零，至少保留一个字节，避免依赖 Shanghai 的 PUSH0
+fn trim(bytes: Vec<u8>) -> Vec<u8> {
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len() - 1);
+    bytes[start..].to_vec()
+}
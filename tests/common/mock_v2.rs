@@ -0,0 +1,361 @@
+//! 本地放置的 Uniswap V2 精简合约：代币、交易对和 Router
+//!
+//! 录制数据中只有 getReserves 所需的状态，执行 swap 需要的代币、交易对和 Router 在这里用 [`super::asm`]
+//! 汇编。存储布局和外部行为与主网合约一致：代币的 `balanceOf` / `allowance` 映射在槽位 3、4
+//! （与 WETH9 相同），交易对的 token0、token1 和储备量在槽位 6、7、8，Router 按
+//! `getPair[tokenA][tokenB]`（槽位 0）查找交易对，代替主网 Router 的 CREATE2 地址计算。
+
+use revm::primitives::{address, keccak256, AccountInfo, Address, Bytes, U256 as rU256};
+use revm_example::{
+    replay::ReplayClient,
+    utils::{mapping_slot, nested_mapping_slot},
+    ForkSimulator, PairReserves,
+};
+
+use super::{asm::assemble, ether, insert_contract};
+
+pub const BALANCE_SLOT: u64 = 3;
+pub const ALLOWANCE_SLOT: u64 = 4;
+
+/// 按地址排序的三个代币，`TOKEN_A` < `TOKEN_B` < `TOKEN_C`
+pub const TOKEN_A: Address = address!("000000000000000000000000000000000000aaaa");
+pub const TOKEN_B: Address = address!("000000000000000000000000000000000000bbbb");
+pub const TOKEN_C: Address = address!("000000000000000000000000000000000000cccc");
+pub const PAIR_AB: Address = address!("00000000000000000000000000000000000a00b0");
+pub const PAIR_BC: Address = address!("00000000000000000000000000000000000b00c0");
+pub const ROUTER: Address = address!("000000000000000000000000000000000000f00d");
+/// 发起兑换的账户，放置时带有 100 ETH
+pub const TRADER: Address = address!("0000000000000000000000000000000000007a7e");
+
+fn topic(signature: &str) -> String {
+    keccak256(signature).to_string()
+}
+
+/// 只实现 balanceOf、allowance、transfer 和 transferFrom 的 ERC-20，余额或授权不足时回滚
+pub fn token_code() -> Bytes {
+    let transfer = topic("Transfer(address,address,uint256)");
+    assemble(&format!(
+        "
+        0x00 CALLDATALOAD 0xe0 SHR
+        DUP1 0x70a08231 EQ @balance_of JUMPI
+        DUP1 0xdd62ed3e EQ @allowance JUMPI
+        DUP1 0xa9059cbb EQ @transfer JUMPI
+        DUP1 0x23b872dd EQ @transfer_from JUMPI
+    fail:
+        0x00 0x00 REVERT
+
+    balance_of:
+        0x04 CALLDATALOAD 0x00 MSTORE {BALANCE_SLOT} 0x20 MSTORE 0x40 0x00 KECCAK256
+        SLOAD 0x00 MSTORE 0x20 0x00 RETURN
+
+    allowance:                          ; keccak(spender . keccak(owner . 4))
+        0x04 CALLDATALOAD 0x00 MSTORE {ALLOWANCE_SLOT} 0x20 MSTORE 0x40 0x00 KECCAK256
+        0x20 MSTORE 0x24 CALLDATALOAD 0x00 MSTORE 0x40 0x00 KECCAK256
+        SLOAD 0x00 MSTORE 0x20 0x00 RETURN
+
+    transfer:                           ; 0x80 from, 0xa0 to, 0xc0 amount
+        CALLER 0x80 MSTORE 0x04 CALLDATALOAD 0xa0 MSTORE 0x24 CALLDATALOAD 0xc0 MSTORE
+        @move JUMP
+
+    transfer_from:
+        0x04 CALLDATALOAD 0x80 MSTORE 0x24 CALLDATALOAD 0xa0 MSTORE 0x44 CALLDATALOAD 0xc0 MSTORE
+        0x80 MLOAD 0x00 MSTORE {ALLOWANCE_SLOT} 0x20 MSTORE 0x40 0x00 KECCAK256
+        0x20 MSTORE CALLER 0x00 MSTORE 0x40 0x00 KECCAK256      ; [slot]
+        DUP1 SLOAD                                              ; [slot, allowance]
+        0xc0 MLOAD DUP2 LT @fail JUMPI
+        0xc0 MLOAD SWAP1 SUB SWAP1 SSTORE
+
+    move:
+        0xc0 MLOAD
+        0x80 MLOAD 0x00 MSTORE {BALANCE_SLOT} 0x20 MSTORE 0x40 0x00 KECCAK256
+        DUP1 SLOAD                                              ; [amount, slot, balance]
+        DUP3 DUP2 LT @fail JUMPI
+        DUP3 DUP2 SUB SWAP1 POP SWAP1 SSTORE                    ; [amount]
+        0xa0 MLOAD 0x00 MSTORE 0x40 0x00 KECCAK256
+        DUP1 SLOAD DUP3 ADD SWAP1 SSTORE
+        0x00 MSTORE 0xa0 MLOAD 0x80 MLOAD {transfer} 0x20 0x00 LOG3
+        0x01 0x00 MSTORE 0x20 0x00 RETURN
+        "
+    ))
+}
+
+/// 实现 getReserves、token0、token1 和 swap 的交易对
+///
+/// swap 按 UniswapV2Pair 的规则执行：先转出，再按余额计算输入并检查扣除 0.3% 手续费后的
+/// 恒定乘积，最后更新储备量并发出 `Sync`、`Swap`。不支持闪电贷回调，也不累计价格。
+pub fn pair_code() -> Bytes {
+    let sync = topic("Sync(uint112,uint112)");
+    let swap = topic("Swap(address,uint256,uint256,uint256,uint256,address)");
+    let mask = "0xffffffffffffffffffffffffffff";
+    assemble(&format!(
+        "
+        0x00 CALLDATALOAD 0xe0 SHR
+        DUP1 0x0902f1ac EQ @get_reserves JUMPI
+        DUP1 0x0dfe1681 EQ @token0 JUMPI
+        DUP1 0xd21220a7 EQ @token1 JUMPI
+        DUP1 0x022c0d9f EQ @swap JUMPI
+    fail:
+        0x00 0x00 REVERT
+
+    get_reserves:
+        0x08 SLOAD DUP1 {mask} AND 0x00 MSTORE
+        DUP1 0x70 SHR {mask} AND 0x20 MSTORE
+        0xe0 SHR 0x40 MSTORE 0x60 0x00 RETURN
+
+    token0:
+        0x06 SLOAD 0x00 MSTORE 0x20 0x00 RETURN
+
+    token1:
+        0x07 SLOAD 0x00 MSTORE 0x20 0x00 RETURN
+
+    ; 0x80 amount0Out, 0xa0 amount1Out, 0xc0 to, 0xe0 reserve0, 0x100 reserve1,
+    ; 0x120 balance0, 0x140 balance1, 0x160 amount0In, 0x180 amount1In
+    swap:
+        0x04 CALLDATALOAD 0x80 MSTORE 0x24 CALLDATALOAD 0xa0 MSTORE 0x44 CALLDATALOAD 0xc0 MSTORE
+        0x80 MLOAD 0xa0 MLOAD OR ISZERO @fail JUMPI
+        0x08 SLOAD DUP1 {mask} AND 0xe0 MSTORE 0x70 SHR {mask} AND 0x100 MSTORE
+        0xe0 MLOAD 0x80 MLOAD LT ISZERO @fail JUMPI
+        0x100 MLOAD 0xa0 MLOAD LT ISZERO @fail JUMPI
+
+        0x80 MLOAD ISZERO @sent0 JUMPI
+        0xa9059cbb 0xe0 SHL 0x200 MSTORE 0xc0 MLOAD 0x204 MSTORE 0x80 MLOAD 0x224 MSTORE
+        0x20 0x200 0x44 0x200 0x00 0x06 SLOAD GAS CALL ISZERO @fail JUMPI
+    sent0:
+        0xa0 MLOAD ISZERO @sent1 JUMPI
+        0xa9059cbb 0xe0 SHL 0x200 MSTORE 0xc0 MLOAD 0x204 MSTORE 0xa0 MLOAD 0x224 MSTORE
+        0x20 0x200 0x44 0x200 0x00 0x07 SLOAD GAS CALL ISZERO @fail JUMPI
+    sent1:
+        0x70a08231 0xe0 SHL 0x200 MSTORE ADDRESS 0x204 MSTORE
+        0x20 0x240 0x24 0x200 0x06 SLOAD GAS STATICCALL ISZERO @fail JUMPI
+        0x240 MLOAD 0x120 MSTORE
+        0x20 0x240 0x24 0x200 0x07 SLOAD GAS STATICCALL ISZERO @fail JUMPI
+        0x240 MLOAD 0x140 MSTORE
+
+        ; amountIn = balance > reserve - amountOut ? balance - (reserve - amountOut) : 0
+        0xe0 MLOAD 0x80 MLOAD SWAP1 SUB 0x120 MLOAD
+        DUP2 DUP2 GT ISZERO @no_in0 JUMPI
+        SUB 0x160 MSTORE @in0 JUMP
+    no_in0:
+        POP POP
+    in0:
+        0x100 MLOAD 0xa0 MLOAD SWAP1 SUB 0x140 MLOAD
+        DUP2 DUP2 GT ISZERO @no_in1 JUMPI
+        SUB 0x180 MSTORE @in1 JUMP
+    no_in1:
+        POP POP
+    in1:
+        0x160 MLOAD 0x180 MLOAD OR ISZERO @fail JUMPI
+
+        ; (balance0 * 1000 - amount0In * 3) * (balance1 * 1000 - amount1In * 3) >= reserve0 * reserve1 * 1000000
+        0x160 MLOAD 0x03 MUL 0x120 MLOAD 0x03e8 MUL SUB
+        0x180 MLOAD 0x03 MUL 0x140 MLOAD 0x03e8 MUL SUB MUL
+        0x0f4240 0x100 MLOAD 0xe0 MLOAD MUL MUL
+        GT @fail JUMPI
+
+        0x120 MLOAD 0x140 MLOAD 0x70 SHL OR TIMESTAMP 0xe0 SHL OR 0x08 SSTORE
+        0x120 MLOAD 0x200 MSTORE 0x140 MLOAD 0x220 MSTORE
+        {sync} 0x40 0x200 LOG1
+        0x160 MLOAD 0x200 MSTORE 0x180 MLOAD 0x220 MSTORE
+        0x80 MLOAD 0x240 MSTORE 0xa0 MLOAD 0x260 MSTORE
+        0xc0 MLOAD CALLER {swap} 0x80 0x200 LOG3
+        STOP
+        "
+    ))
+}
+
+/// 实现 getAmountsOut、getAmountsIn 和 swapExactTokensForTokens 的 Router
+///
+/// 计算公式与 UniswapV2Library 相同，swap 按路径逐跳调用交易对的 `swap`，
+/// 中间跳的输出直接发给下一个交易对。
+pub fn router_code() -> Bytes {
+    assemble(
+        "
+        0x00 CALLDATALOAD 0xe0 SHR
+        DUP1 0xd06ca61f EQ @get_amounts_out JUMPI
+        DUP1 0x1f00ca74 EQ @get_amounts_in JUMPI
+        DUP1 0x38ed1739 EQ @swap_exact JUMPI
+    fail:
+        0x00 0x00 REVERT
+
+    ; 0x80 路径在 calldata 中的位置，0xa0 路径长度，0xc0 输入数量，0xe0 / 0x100 当前一跳的两个代币，
+    ; 0x120 交易对，0x140 / 0x160 输入、输出储备量，0x180 循环变量，0x1a0 当前交易对，
+    ; 0x1c0 接收者，0x1e0 / 0x200 amount0Out / amount1Out，0x300 起为外部调用的缓冲区，
+    ; 0x7c0 起为返回的 amounts 数组
+    get_amounts_out:
+        0x04 CALLDATALOAD 0xc0 MSTORE
+        0x24 CALLDATALOAD 0x04 ADD DUP1 0x80 MSTORE CALLDATALOAD 0xa0 MSTORE
+        @return_amounts @amounts_out JUMP
+
+    get_amounts_in:
+        0x04 CALLDATALOAD 0xc0 MSTORE
+        0x24 CALLDATALOAD 0x04 ADD DUP1 0x80 MSTORE CALLDATALOAD 0xa0 MSTORE
+        @return_amounts @amounts_in JUMP
+
+    return_amounts:
+        0x20 0x7c0 MSTORE 0xa0 MLOAD 0x7e0 MSTORE
+        0xa0 MLOAD 0x20 MUL 0x40 ADD 0x7c0 RETURN
+
+    get_pair:                           ; getPair[a][b]，不存在时回滚
+        0xe0 MLOAD 0x00 MSTORE 0x00 0x20 MSTORE 0x40 0x00 KECCAK256
+        0x20 MSTORE 0x100 MLOAD 0x00 MSTORE 0x40 0x00 KECCAK256 SLOAD
+        DUP1 ISZERO @fail JUMPI
+        0x120 MSTORE JUMP
+
+    load_reserves:                      ; 按 a、b 的顺序取出储备量
+        @lr_pair @get_pair JUMP
+    lr_pair:
+        0x0902f1ac 0xe0 SHL 0x300 MSTORE
+        0x60 0x300 0x04 0x300 0x120 MLOAD GAS STATICCALL ISZERO @fail JUMPI
+        0x100 MLOAD 0xe0 MLOAD LT @lr_ordered JUMPI
+        0x320 MLOAD 0x140 MSTORE 0x300 MLOAD 0x160 MSTORE JUMP
+    lr_ordered:
+        0x300 MLOAD 0x140 MSTORE 0x320 MLOAD 0x160 MSTORE JUMP
+
+    amounts_out:                        ; amounts[i + 1] = getAmountOut(amounts[i], ...)
+        0xc0 MLOAD 0x800 MSTORE
+        0x00 0x180 MSTORE
+    ao_loop:
+        0xa0 MLOAD 0x180 MLOAD 0x01 ADD LT ISZERO @ao_done JUMPI
+        0x180 MLOAD 0x20 MUL 0x80 MLOAD ADD 0x20 ADD
+        DUP1 CALLDATALOAD 0xe0 MSTORE 0x20 ADD CALLDATALOAD 0x100 MSTORE
+        @ao_reserves @load_reserves JUMP
+    ao_reserves:
+        0x160 MLOAD ISZERO @fail JUMPI
+        0x180 MLOAD 0x20 MUL 0x800 ADD MLOAD 0x03e5 MUL
+        DUP1 0x160 MLOAD MUL
+        SWAP1 0x140 MLOAD 0x03e8 MUL ADD
+        SWAP1 DIV
+        0x180 MLOAD 0x01 ADD 0x20 MUL 0x800 ADD MSTORE
+        0x180 MLOAD 0x01 ADD 0x180 MSTORE
+        @ao_loop JUMP
+    ao_done:
+        JUMP
+
+    amounts_in:                         ; amounts[i - 1] = getAmountIn(amounts[i], ...)
+        0xc0 MLOAD 0xa0 MLOAD 0x01 SWAP1 SUB
+        DUP1 0x180 MSTORE 0x20 MUL 0x800 ADD MSTORE
+    ai_loop:
+        0x180 MLOAD ISZERO @ai_done JUMPI
+        0x180 MLOAD 0x20 MUL 0x80 MLOAD ADD
+        DUP1 CALLDATALOAD 0xe0 MSTORE 0x20 ADD CALLDATALOAD 0x100 MSTORE
+        @ai_reserves @load_reserves JUMP
+    ai_reserves:
+        0x180 MLOAD 0x20 MUL 0x800 ADD MLOAD
+        DUP1 0x160 MLOAD GT ISZERO @fail JUMPI
+        DUP1 0x160 MLOAD SUB 0x03e5 MUL
+        SWAP1 0x140 MLOAD MUL 0x03e8 MUL
+        DIV 0x01 ADD
+        0x180 MLOAD 0x01 SWAP1 SUB
+        DUP1 0x180 MSTORE 0x20 MUL 0x800 ADD MSTORE
+        @ai_loop JUMP
+    ai_done:
+        JUMP
+
+    swap_exact:
+        0x04 CALLDATALOAD 0xc0 MSTORE
+        0x44 CALLDATALOAD 0x04 ADD DUP1 0x80 MSTORE CALLDATALOAD 0xa0 MSTORE
+        TIMESTAMP 0x84 CALLDATALOAD LT @fail JUMPI
+        @se_quoted @amounts_out JUMP
+    se_quoted:
+        0x24 CALLDATALOAD 0xa0 MLOAD 0x01 SWAP1 SUB 0x20 MUL 0x800 ADD MLOAD LT @fail JUMPI
+        0x80 MLOAD 0x20 ADD DUP1 CALLDATALOAD 0xe0 MSTORE 0x20 ADD CALLDATALOAD 0x100 MSTORE
+        @se_first_pair @get_pair JUMP
+    se_first_pair:                      ; transferFrom(msg.sender, pair, amounts[0])
+        0x23b872dd 0xe0 SHL 0x300 MSTORE CALLER 0x304 MSTORE
+        0x120 MLOAD 0x324 MSTORE 0x800 MLOAD 0x344 MSTORE
+        0x20 0x300 0x64 0x300 0x00 0xe0 MLOAD GAS CALL ISZERO @fail JUMPI
+        0x00 0x180 MSTORE
+    se_loop:
+        0xa0 MLOAD 0x180 MLOAD 0x01 ADD LT ISZERO @return_amounts JUMPI
+        0x180 MLOAD 0x20 MUL 0x80 MLOAD ADD 0x20 ADD
+        DUP1 CALLDATALOAD 0xe0 MSTORE 0x20 ADD CALLDATALOAD 0x100 MSTORE
+        @se_pair @get_pair JUMP
+    se_pair:
+        0x120 MLOAD 0x1a0 MSTORE
+        0x00 0x1e0 MSTORE 0x00 0x200 MSTORE
+        0x180 MLOAD 0x01 ADD 0x20 MUL 0x800 ADD MLOAD
+        0x100 MLOAD 0xe0 MLOAD LT @se_zero_for_one JUMPI
+        0x1e0 MSTORE @se_outs JUMP
+    se_zero_for_one:
+        0x200 MSTORE
+    se_outs:                            ; 最后一跳发给 to，其余发给下一个交易对
+        0x64 CALLDATALOAD 0x1c0 MSTORE
+        0xa0 MLOAD 0x180 MLOAD 0x02 ADD LT ISZERO @se_call JUMPI
+        0x100 MLOAD 0xe0 MSTORE
+        0x180 MLOAD 0x02 ADD 0x20 MUL 0x80 MLOAD ADD 0x20 ADD CALLDATALOAD 0x100 MSTORE
+        @se_next_pair @get_pair JUMP
+    se_next_pair:
+        0x120 MLOAD 0x1c0 MSTORE
+    se_call:                            ; pair.swap(amount0Out, amount1Out, to, \"\")
+        0x022c0d9f 0xe0 SHL 0x300 MSTORE
+        0x1e0 MLOAD 0x304 MSTORE 0x200 MLOAD 0x324 MSTORE 0x1c0 MLOAD 0x344 MSTORE
+        0x80 0x364 MSTORE 0x00 0x384 MSTORE
+        0x00 0x00 0xa4 0x300 0x00 0x1a0 MLOAD GAS CALL ISZERO @fail JUMPI
+        0x180 MLOAD 0x01 ADD 0x180 MSTORE
+        @se_loop JUMP
+        ",
+    )
+}
+
+/// 放置三个代币、A-B 和 B-C 两个交易对、Router 以及 [`TRADER`]，交易对持有与储备量相同的代币
+pub fn deploy_v2(
+    simulator: &mut ForkSimulator<ReplayClient>,
+    reserves_ab: (u128, u128),
+    reserves_bc: (u128, u128),
+) {
+    simulator
+        .cache_db_mut()
+        .insert_account_info(TRADER, AccountInfo::from_balance(ether(100)));
+    for token in [TOKEN_A, TOKEN_B, TOKEN_C] {
+        insert_contract(simulator, token, token_code());
+    }
+    insert_contract(simulator, ROUTER, router_code());
+    for (pair, token0, token1, (reserve0, reserve1)) in [
+        (PAIR_AB, TOKEN_A, TOKEN_B, reserves_ab),
+        (PAIR_BC, TOKEN_B, TOKEN_C, reserves_bc),
+    ] {
+        insert_contract(simulator, pair, pair_code());
+        let db = simulator.cache_db_mut();
+        db.insert_account_storage(pair, rU256::from(6), token0.into_word().into())
+            .unwrap();
+        db.insert_account_storage(pair, rU256::from(7), token1.into_word().into())
+            .unwrap();
+        for (token, balance) in [(token0, reserve0), (token1, reserve1)] {
+            db.insert_account_storage(
+                token,
+                mapping_slot(pair, rU256::from(BALANCE_SLOT)),
+                rU256::from(balance),
+            )
+            .unwrap();
+        }
+        for (a, b) in [(token0, token1), (token1, token0)] {
+            db.insert_account_storage(
+                ROUTER,
+                nested_mapping_slot(a, b, rU256::ZERO),
+                pair.into_word().into(),
+            )
+            .unwrap();
+        }
+        simulator
+            .set_pair_reserves(
+                pair,
+                PairReserves {
+                    reserve0,
+                    reserve1,
+                    block_timestamp_last: 0,
+                },
+            )
+            .unwrap();
+    }
+}
+
+/// 读取代币合约中的余额
+pub fn token_balance(
+    simulator: &mut ForkSimulator<ReplayClient>,
+    token: Address,
+    holder: Address,
+) -> rU256 {
+    simulator
+        .load_storage(token, mapping_slot(holder, rU256::from(BALANCE_SLOT)))
+        .unwrap()
+}
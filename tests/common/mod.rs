@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+
+pub mod asm;
+pub mod mock_v2;
+
+use ethers_contract::BaseContract;
+use ethers_core::{
+    abi::parse_abi,
+    k256::ecdsa::SigningKey,
+    types::{transaction::eip2718::TypedTransaction, BlockId, Bytes as eBytes, Signature, U256},
+};
+use ethers_providers::Provider;
+use hex_literal::hex;
+use revm::primitives::{address, AccountInfo, Bytecode, Bytes, HashMap, B256, U256 as rU256};
+use revm_example::{replay::ReplayClient, ForkOptions, ForkSimulator};
+use revm_primitives::Address;
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+/// 录制数据所在的区块高度
+pub const FORK_BLOCK: u64 = 17_830_000;
+
+/// 测试中放置本地合约和账户使用的地址
+pub const COUNTER: Address = address!("00000000000000000000000000000000000c0c0c");
+pub const REVERTER: Address = address!("00000000000000000000000000000000000bad00");
+pub const SENDER: Address = address!("5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e");
+
+/// 每次调用把槽位 0 加一，以 LOG0 发出并返回新值
+pub const COUNTER_CODE: [u8; 24] = hex!("600054600101806000558060005260206000a060206000f3");
+/// 不带数据直接 REVERT
+pub const REVERTER_CODE: [u8; 5] = hex!("60006000fd");
+
+pub fn ether(amount: u64) -> rU256 {
+    rU256::from(amount) * rU256::from(10).pow(rU256::from(18))
+}
+
+pub fn slot(value: u64) -> B256 {
+    B256::from(rU256::from(value))
+}
+
+/// 用测试私钥签名交易，返回 RLP 编码的原始交易
+pub fn sign_transaction(key: &SigningKey, tx: TypedTransaction) -> eBytes {
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(tx.sighash().as_bytes())
+        .unwrap();
+    let signature = Signature {
+        r: U256::from_big_endian(&signature.r().to_bytes()),
+        s: U256::from_big_endian(&signature.s().to_bytes()),
+        v: recovery_id.to_byte().into(),
+    };
+    tx.rlp_signed(&signature)
+}
+
+/// Uniswap V2 WETH-USDT 池
+pub fn pool_address() -> Address {
+    Address::from_str("0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852").unwrap()
+}
+
+pub fn pair_contract() -> BaseContract {
+    BaseContract::from(
+        parse_abi(&["function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)"])
+            .unwrap(),
+    )
+}
+
+pub fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// 从录制文件创建回放客户端
+///
+/// 录制中池子的代码是一个只实现了 getReserves 的精简合约：读取槽位 8 并按
+/// UniswapV2Pair 的打包方式拆成 (reserve0, reserve1, blockTimestampLast) 返回，
+/// 槽位 8 的值与 main.rs 注释中的真实值一致。
+pub fn replay_client() -> ReplayClient {
+    ReplayClient::from_file(fixture("weth_usdt_get_reserves.json")).unwrap()
+}
+
+pub fn fork_options() -> ForkOptions {
+    ForkOptions {
+        fork_block: Some(BlockId::from(FORK_BLOCK)),
+        ..Default::default()
+    }
+}
+
+pub async fn replay_simulator(
+    client: ReplayClient,
+    options: ForkOptions,
+) -> ForkSimulator<ReplayClient> {
+    ForkSimulator::new(Arc::new(Provider::new(client)), options)
+        .await
+        .unwrap()
+}
+
+/// 在分叉状态中放置一个本地合约，未写入的存储槽视为 0，不会请求节点
+pub fn insert_contract(simulator: &mut ForkSimulator<ReplayClient>, address: Address, code: Bytes) {
+    let bytecode = Bytecode::new_raw(code);
+    simulator.insert_account_info(
+        address,
+        AccountInfo::new(rU256::ZERO, 1, bytecode.hash_slow(), bytecode),
+    );
+    simulator
+        .replace_account_storage(address, HashMap::default())
+        .unwrap();
+}
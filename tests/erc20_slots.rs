@@ -0,0 +1,65 @@
+mod common;
+
+use common::*;
+use ethers_core::types::U256;
+use hex_literal::hex;
+use revm::primitives::{Bytes, U256 as rU256};
+use revm_example::{erc20::erc20_contract, utils::to_ethers_address, MappingLayout, MappingSlot};
+use revm_primitives::{address, Address};
+
+const TOKEN: Address = address!("00000000000000000000000000000000000e2c20");
+const HOLDER: Address = address!("1111111111111111111111111111111111111111");
+const SPENDER: Address = address!("2222222222222222222222222222222222222222");
+
+/// 手工汇编的精简代币：
+/// - 每次调用先读取槽位 0（模拟 paused 标志），用来干扰槽位搜索
+/// - `balanceOf` 为 Solidity 布局，映射位于槽位 3
+/// - `allowance` 为 Vyper 布局，映射位于槽位 4
+const TOKEN_CODE: [u8; 99] = hex!(
+    "6000545060003560e01c806370a082311460215763dd62ed3e14603b57600080fd5b6004356000526003"
+    "60205260406000205460005260206000f35b6004600052600435602052604060002060005260243560205260"
+    "406000205460005260206000f3"
+);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn discovers_and_overrides_token_slots() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, TOKEN, Bytes::from_static(&TOKEN_CODE));
+
+    let balance_slot = simulator.find_balance_slot(TOKEN).unwrap();
+    assert_eq!(balance_slot, MappingSlot::solidity(3));
+    let allowance_slot = simulator.find_allowance_slot(TOKEN).unwrap();
+    assert_eq!(
+        allowance_slot,
+        MappingSlot {
+            index: rU256::from(4),
+            layout: MappingLayout::Vyper,
+        }
+    );
+
+    simulator
+        .set_balance(TOKEN, HOLDER, rU256::from(1_000))
+        .unwrap();
+    simulator
+        .set_allowance(TOKEN, HOLDER, SPENDER, rU256::from(500))
+        .unwrap();
+    let erc20 = erc20_contract();
+    let balance: U256 = simulator
+        .call_method(&erc20, TOKEN, "balanceOf", to_ethers_address(HOLDER))
+        .unwrap();
+    assert_eq!(balance, U256::from(1_000));
+    let allowance: U256 = simulator
+        .call_method(
+            &erc20,
+            TOKEN,
+            "allowance",
+            (to_ethers_address(HOLDER), to_ethers_address(SPENDER)),
+        )
+        .unwrap();
+    assert_eq!(allowance, U256::from(500));
+    // 其他持有者不受影响
+    let balance: U256 = simulator
+        .call_method(&erc20, TOKEN, "balanceOf", to_ethers_address(SPENDER))
+        .unwrap();
+    assert!(balance.is_zero());
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::*;
+use ethers_core::abi::{encode, parse_abi, Token};
+use revm::primitives::{Address, Bytes, InvalidTransaction, TransactTo, TxEnv, U256 as rU256};
+use revm_example::{
+    revert::{ERROR_SELECTOR, PANIC_SELECTOR},
+    router::UNISWAP_V2_ROUTER02,
+    PairReserves, RevertReason, SimulationError,
+};
+
+/// 把 `data` 拷贝到内存后直接 REVERT 的合约
+fn reverting_code(data: &[u8]) -> Bytes {
+    let len = u8::try_from(data.len()).unwrap();
+    let mut code = vec![
+        0x60, len, 0x60, 0x0c, 0x60, 0x00, 0x39, // CODECOPY(0, 12, len)
+        0x60, len, 0x60, 0x00, 0xfd, // REVERT(0, len)
+    ];
+    code.extend_from_slice(data);
+    code.into()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn revert_carries_decoded_reason() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let mut data = ERROR_SELECTOR.to_vec();
+    data.extend(encode(&[Token::String("nope".into())]));
+    insert_contract(&mut simulator, REVERTER, reverting_code(&data));
+
+    let error = simulator.call(REVERTER, Bytes::new()).unwrap_err();
+    assert!(matches!(error, SimulationError::Revert { .. }));
+    assert_eq!(error.revert_output().unwrap().as_ref(), data.as_slice());
+    assert_eq!(error.to_string(), "调用回滚: nope");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn validation_and_rpc_errors_are_distinguished() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    // 零地址的余额不足以支付转账金额，交易在执行前被拒绝
+    let error = simulator
+        .transact(TxEnv {
+            caller: Address::ZERO,
+            transact_to: TransactTo::Call(pool_address()),
+            value: rU256::from(10).pow(rU256::from(24)),
+            gas_limit: 100_000,
+            gas_price: simulator.block_env().basefee,
+            ..Default::default()
+        })
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SimulationError::InvalidTransaction(InvalidTransaction::LackOfFundForMaxFee { .. })
+    ));
+
+    // 回放数据中没有录制的账户会导致节点请求失败
+    let error = simulator.load_account(REVERTER).unwrap_err();
+    assert!(matches!(error, SimulationError::Rpc(_)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn panic_code_is_explained() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let mut data = PANIC_SELECTOR.to_vec();
+    data.extend(encode(&[Token::Uint(0x11.into())]));
+    insert_contract(&mut simulator, REVERTER, reverting_code(&data));
+
+    let error = simulator.call(REVERTER, Bytes::new()).unwrap_err();
+    assert_eq!(
+        error.revert_reason(),
+        Some(RevertReason::Panic(rU256::from(0x11)))
+    );
+    assert_eq!(
+        error.to_string(),
+        "调用回滚: Panic(0x11): 算术运算上溢或下溢"
+    );
+}
+
+#[test]
+fn custom_errors_are_resolved_against_abi() {
+    let abi = parse_abi(&[
+        "error InsufficientBalance(uint256 available, uint256 required)",
+        "error Unauthorized(address caller)",
+    ])
+    .unwrap();
+    let selector = &abi.error("InsufficientBalance").unwrap().signature()[..4];
+    let mut data = selector.to_vec();
+    data.extend(encode(&[Token::Uint(5.into()), Token::Uint(10.into())]));
+
+    let reason = RevertReason::decode_with(&data, Some(&abi));
+    assert_eq!(reason.to_string(), "InsufficientBalance(5, 10)");
+    // 没有提供 ABI 时保留原始数据
+    assert_eq!(
+        RevertReason::decode(&data),
+        RevertReason::Unknown(Bytes::from(data))
+    );
+    assert_eq!(RevertReason::decode(&[]), RevertReason::Empty);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn helper_errors_are_typed() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+
+    // 计数器不是代币，balanceOf 读取的槽位都无法验证
+    let error = simulator.find_balance_slot(COUNTER).unwrap_err();
+    assert!(matches!(error, SimulationError::MappingSlotNotFound(token) if token == COUNTER));
+
+    let reserves = PairReserves {
+        reserve0: 1 << 112,
+        ..Default::default()
+    };
+    let error = simulator
+        .set_pair_reserves(pool_address(), reserves)
+        .unwrap_err();
+    assert!(matches!(error, SimulationError::InvalidArgument(_)));
+
+    let error = simulator
+        .router_amounts_out(UNISWAP_V2_ROUTER02, rU256::from(1), &[COUNTER])
+        .unwrap_err();
+    assert!(matches!(error, SimulationError::InvalidArgument(_)));
+
+    let error = simulator
+        .call_method::<_, (u128, u128, u32)>(&pair_contract(), COUNTER, "getReserves", ())
+        .unwrap_err();
+    assert!(matches!(error, SimulationError::Abi(_)));
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::*;
+use hex_literal::hex;
+use revm::primitives::{address, AccountInfo, Address, Bytes, TransactTo, TxEnv, U256 as rU256};
+use revm_example::{replay::ReplayClient, ForkSimulator, RevertReason, SimulationError};
+
+const CLEARER: Address = address!("00000000000000000000000000000000000c1ea0");
+const FORWARDER: Address = address!("00000000000000000000000000000000000f0f0f");
+
+/// 把槽位 0 清零，产生存储退款
+const CLEARER_CODE: [u8; 6] = hex!("600060005500");
+
+/// 把全部剩余 gas 转给计数器，调用失败时回滚
+fn forwarder_code() -> Bytes {
+    hex::decode(format!(
+        "6000600060006000600073{}5af1602857600080fd5b00",
+        hex::encode(COUNTER)
+    ))
+    .unwrap()
+    .into()
+}
+
+fn call(to: Address) -> TxEnv {
+    TxEnv {
+        transact_to: TransactTo::Call(to),
+        ..Default::default()
+    }
+}
+
+async fn simulator() -> ForkSimulator<ReplayClient> {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    insert_contract(&mut simulator, CLEARER, Bytes::from_static(&CLEARER_CODE));
+    insert_contract(&mut simulator, FORWARDER, forwarder_code());
+    insert_contract(&mut simulator, REVERTER, Bytes::from_static(&REVERTER_CODE));
+    simulator
+        .cache_db_mut()
+        .insert_account_storage(CLEARER, rU256::ZERO, rU256::from(1))
+        .unwrap();
+    simulator
+}
+
+/// 估算结果恰好是能成功执行的最小 gas 上限
+fn assert_minimal(simulator: &mut ForkSimulator<ReplayClient>, to: Address, gas: u64) {
+    let run = |simulator: &mut ForkSimulator<_>, gas_limit| {
+        simulator
+            .simulate(TxEnv {
+                gas_limit,
+                ..call(to)
+            })
+            .unwrap()
+            .result
+    };
+    assert!(run(simulator, gas).is_success());
+    assert!(!run(simulator, gas - 1).is_success());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn estimate_is_minimal_gas_limit() {
+    let mut simulator = simulator().await;
+
+    let gas = simulator.estimate_gas(call(COUNTER)).unwrap();
+    assert_minimal(&mut simulator, COUNTER, gas);
+
+    // 退款在执行结束后才返还，上限需要高于实际消耗
+    let gas = simulator.estimate_gas(call(CLEARER)).unwrap();
+    let gas_used = simulator.simulate(call(CLEARER)).unwrap().result.gas_used();
+    assert!(gas > gas_used, "{gas} <= {gas_used}");
+    assert_minimal(&mut simulator, CLEARER, gas);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn estimate_reserves_gas_for_nested_calls() {
+    let mut simulator = simulator().await;
+
+    let gas = simulator.estimate_gas(call(FORWARDER)).unwrap();
+    assert_minimal(&mut simulator, FORWARDER, gas);
+    // 内层调用只能拿到剩余 gas 的 63/64，外层需要的上限高于实际消耗
+    let gas_used = simulator
+        .simulate(call(FORWARDER))
+        .unwrap()
+        .result
+        .gas_used();
+    assert!(gas > gas_used, "{gas} <= {gas_used}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn estimate_distinguishes_revert_from_out_of_gas() {
+    let mut simulator = simulator().await;
+
+    let error = simulator.estimate_gas(call(REVERTER)).unwrap_err();
+    assert_eq!(error.revert_reason(), Some(RevertReason::Empty));
+
+    let error = simulator
+        .estimate_gas(TxEnv {
+            gas_limit: 30_000,
+            ..call(COUNTER)
+        })
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SimulationError::GasRequiredExceedsAllowance(30_000)
+    ));
+
+    // gas 价格不为 0 时上限受发送者余额限制
+    simulator.cache_db_mut().insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: rU256::from(30_000),
+            ..Default::default()
+        },
+    );
+    let error = simulator
+        .estimate_gas(TxEnv {
+            caller: SENDER,
+            gas_price: rU256::from(1),
+            ..call(COUNTER)
+        })
+        .unwrap_err();
+    assert!(
+        matches!(error, SimulationError::GasRequiredExceedsAllowance(30_000)),
+        "{error:?}"
+    );
+}
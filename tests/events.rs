@@ -0,0 +1,270 @@
+mod common;
+
+use common::{mock_v2::*, *};
+use ethers_contract::BaseContract;
+use ethers_core::{
+    abi::{parse_abi, Token},
+    types::Bytes as eBytes,
+};
+use revm::primitives::{
+    address, keccak256, AccountInfo, Address, Bytecode, Bytes, Log, TransactTo, TxEnv, B256,
+    U256 as rU256,
+};
+use revm_example::{
+    utils::{to_ethers_address, to_ethers_u256},
+    KnownEvent, LogDecoder, MappingSlot, V2Swap,
+};
+
+const TOKEN: Address = address!("00000000000000000000000000000000000e2c20");
+const ALICE: Address = address!("00000000000000000000000000000000000a11ce");
+const BOB: Address = address!("0000000000000000000000000000000000000b0b");
+
+fn topic(address: Address) -> B256 {
+    address.into_word()
+}
+
+fn words(values: &[u64]) -> Bytes {
+    values
+        .iter()
+        .flat_map(|value| rU256::from(*value).to_be_bytes::<32>())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[test]
+fn decodes_standard_events() {
+    let decoder = LogDecoder::new();
+    let transfer = Log::new_unchecked(
+        TOKEN,
+        vec![
+            keccak256("Transfer(address,address,uint256)"),
+            topic(ALICE),
+            topic(BOB),
+        ],
+        words(&[1_000]),
+    );
+    let sync = Log::new_unchecked(
+        pool_address(),
+        vec![keccak256("Sync(uint112,uint112)")],
+        words(&[7, 9]),
+    );
+    let swap = Log::new_unchecked(
+        pool_address(),
+        vec![
+            keccak256("Swap(address,uint256,uint256,uint256,uint256,address)"),
+            topic(ALICE),
+            topic(BOB),
+        ],
+        words(&[0, 100, 50, 0]),
+    );
+    // ERC721 的 Transfer 与 ERC20 共用 topic0，但参数全部是 indexed，不应被误解码
+    let nft_transfer = Log::new_unchecked(
+        TOKEN,
+        vec![
+            keccak256("Transfer(address,address,uint256)"),
+            topic(ALICE),
+            topic(BOB),
+            B256::from(rU256::from(1)),
+        ],
+        Bytes::new(),
+    );
+    let unknown = Log::new_unchecked(TOKEN, vec![keccak256("Foo()")], Bytes::new());
+
+    let decoded =
+        decoder.decode_logs(&[transfer, unknown.clone(), sync, nft_transfer.clone(), swap]);
+    assert_eq!(decoded.len(), 3);
+    assert!(decoder.decode(&unknown).is_none());
+    assert!(decoder.decode(&nft_transfer).is_none());
+
+    assert_eq!(decoded[0].address, TOKEN);
+    assert_eq!(decoded[0].name, "Transfer");
+    assert_eq!(
+        decoded[0].event(),
+        Some(KnownEvent::Transfer {
+            from: ALICE,
+            to: BOB,
+            value: rU256::from(1_000),
+        })
+    );
+    assert_eq!(
+        decoded[0].to_string(),
+        format!(
+            "{TOKEN}::Transfer(from: {}, to: {}, value: 1000)",
+            ALICE.to_string().to_lowercase(),
+            BOB.to_string().to_lowercase()
+        )
+    );
+    assert_eq!(
+        decoded[1].event(),
+        Some(KnownEvent::Sync {
+            reserve0: 7,
+            reserve1: 9,
+        })
+    );
+    assert_eq!(
+        decoded[2].event(),
+        Some(KnownEvent::Swap {
+            sender: ALICE,
+            amount0_in: rU256::ZERO,
+            amount1_in: rU256::from(100),
+            amount0_out: rU256::from(50),
+            amount1_out: rU256::ZERO,
+            to: BOB,
+        })
+    );
+}
+
+#[test]
+fn decodes_events_from_supplied_abi() {
+    let abi = parse_abi(&["event Deposit(address indexed dst, uint wad)"]).unwrap();
+    let decoder = LogDecoder::new().with_abi(&abi);
+    let log = Log::new_unchecked(
+        TOKEN,
+        vec![keccak256("Deposit(address,uint256)"), topic(ALICE)],
+        words(&[42]),
+    );
+
+    let decoded = decoder.decode(&log).unwrap();
+    assert_eq!(decoded.name, "Deposit");
+    assert_eq!(decoded.param("wad"), Some(&Token::Uint(42.into())));
+    assert_eq!(decoded.event(), None);
+    assert!(LogDecoder::new().decode(&log).is_none());
+}
+
+/// 读取槽位 8，按交易对的打包方式拆出储备量，发出 `Sync(reserve0, reserve1)`
+fn sync_emitter_code() -> Bytes {
+    let mask = "ff".repeat(14);
+    let topic = hex::encode(keccak256("Sync(uint112,uint112)"));
+    hex::decode(format!(
+        "600854806d{mask}1660005260701c6d{mask}166020527f{topic}60406000a100"
+    ))
+    .unwrap()
+    .into()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_event_matches_reserves_slot() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let pool = pool_address();
+    // 替换池子的代码，存储仍然从录制中读取
+    let bytecode = Bytecode::new_raw(sync_emitter_code());
+    simulator.cache_db_mut().insert_account_info(
+        pool,
+        AccountInfo::new(rU256::ZERO, 1, bytecode.hash_slow(), bytecode),
+    );
+
+    let result = simulator
+        .simulate(TxEnv {
+            transact_to: TransactTo::Call(pool),
+            ..Default::default()
+        })
+        .unwrap()
+        .result;
+    let logs = LogDecoder::new().decode_logs(result.logs());
+    assert_eq!(logs.len(), 1);
+
+    let reserves = simulator.pair_reserves(pool).unwrap();
+    assert_eq!(
+        logs[0].event(),
+        Some(KnownEvent::Sync {
+            reserve0: reserves.reserve0,
+            reserve1: reserves.reserve1,
+        })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn decodes_events_from_v2_swap() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let (reserve0, reserve1) = (5_000_000_000_000_000_000_000u128, 12_000_000_000_000u128);
+    deploy_v2(&mut simulator, (reserve0, reserve1), (1, 1));
+    let amount_in = ether(3);
+
+    let outcome = simulator
+        .simulate_v2_swap(&V2Swap {
+            pair: PAIR_AB,
+            token_in: TOKEN_A,
+            amount_in,
+            balance_slot: Some(MappingSlot::solidity(BALANCE_SLOT)),
+            amount_out: None,
+            to: TRADER,
+        })
+        .unwrap();
+    let amount_out = outcome.amount_out();
+    let events: Vec<_> = outcome
+        .logs
+        .iter()
+        .map(|log| (log.address, log.event().unwrap()))
+        .collect();
+    let sync = KnownEvent::Sync {
+        reserve0: outcome.reserves_after.reserve0,
+        reserve1: outcome.reserves_after.reserve1,
+    };
+    assert_eq!(
+        events,
+        vec![
+            (
+                TOKEN_B,
+                KnownEvent::Transfer {
+                    from: PAIR_AB,
+                    to: TRADER,
+                    value: amount_out,
+                }
+            ),
+            (PAIR_AB, sync.clone()),
+            (
+                PAIR_AB,
+                KnownEvent::Swap {
+                    sender: TRADER,
+                    amount0_in: amount_in,
+                    amount1_in: rU256::ZERO,
+                    amount0_out: rU256::ZERO,
+                    amount1_out: amount_out,
+                    to: TRADER,
+                }
+            ),
+        ]
+    );
+
+    // 真正执行同一笔 swap 后，交易对存储中的储备量与模拟时的 Sync 一致
+    simulator
+        .cache_db_mut()
+        .insert_account_storage(
+            TOKEN_A,
+            MappingSlot::solidity(BALANCE_SLOT).slot(PAIR_AB),
+            rU256::from(reserve0) + amount_in,
+        )
+        .unwrap();
+    let calldata = BaseContract::from(
+        parse_abi(&["function swap(uint amount0Out, uint amount1Out, address to, bytes data)"])
+            .unwrap(),
+    )
+    .encode(
+        "swap",
+        (
+            to_ethers_u256(rU256::ZERO),
+            to_ethers_u256(amount_out),
+            to_ethers_address(TRADER),
+            eBytes::new(),
+        ),
+    )
+    .unwrap();
+    let receipt = simulator
+        .commit(TxEnv {
+            caller: TRADER,
+            transact_to: TransactTo::Call(PAIR_AB),
+            data: calldata.0.into(),
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(receipt.success());
+    let reserves = simulator.pair_reserves(PAIR_AB).unwrap();
+    assert_eq!(
+        sync,
+        KnownEvent::Sync {
+            reserve0: reserves.reserve0,
+            reserve1: reserves.reserve1,
+        }
+    );
+    assert_eq!(reserves, outcome.reserves_after);
+}
@@ -0,0 +1,201 @@
+use ethers_core::types::BlockId;
+use ethers_providers::Provider;
+use revm::primitives::{address, b256, SpecId, U256 as rU256};
+use revm_example::{replay::ReplayClient, ForkOptions, ForkSimulator, SimulationError, StateCache};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const NUMBER: u64 = 20_000_000;
+
+/// 只包含某个硬分叉之前字段的区块头，`extra` 中的字段追加在后面
+fn header(timestamp: u64, extra: Value) -> Value {
+    header_at(NUMBER, timestamp, extra)
+}
+
+fn header_at(number: u64, timestamp: u64, extra: Value) -> Value {
+    let mut header = json!({
+        "number": format!("{number:#x}"),
+        "hash": format!("0x{}", "6b".repeat(32)),
+        "parentHash": format!("0x{}", "6a".repeat(32)),
+        "miner": "0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5",
+        "timestamp": format!("{timestamp:#x}"),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "difficulty": "0x0",
+        "mixHash": format!("0x{}", "5d".repeat(32)),
+        "nonce": "0x0000000000000000",
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "extraData": "0x",
+        "transactions": [],
+        "uncles": [],
+    });
+    header
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    header
+}
+
+async fn fork_at(
+    chain_id: u64,
+    header: Value,
+) -> Result<ForkSimulator<ReplayClient>, SimulationError> {
+    let number = header["number"].clone();
+    let client = ReplayClient::default();
+    client.insert("eth_getBlockByNumber", json!([number, false]), header);
+    let options = ForkOptions {
+        fork_block: Some(BlockId::from(
+            u64::from_str_radix(number.as_str().unwrap().trim_start_matches("0x"), 16).unwrap(),
+        )),
+        chain_id: Some(chain_id),
+        cache_dir: None,
+    };
+    ForkSimulator::new(Arc::new(Provider::new(client)), options).await
+}
+
+fn cancun_fields() -> Value {
+    json!({
+        "baseFeePerGas": "0x3b9aca00",
+        "withdrawalsRoot": format!("0x{}", "7e".repeat(32)),
+        "blobGasUsed": "0x20000",
+        "excessBlobGas": "0x1312d00",
+        "parentBeaconBlockRoot": format!("0x{}", "7f".repeat(32)),
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spec_follows_header_fields() {
+    let cases = [
+        (json!({}), SpecId::BERLIN),
+        (
+            json!({ "difficulty": "0x1", "baseFeePerGas": "0x1" }),
+            SpecId::LONDON,
+        ),
+        (json!({ "baseFeePerGas": "0x1" }), SpecId::MERGE),
+        (
+            json!({ "baseFeePerGas": "0x1", "withdrawalsRoot": format!("0x{}", "7e".repeat(32)) }),
+            SpecId::SHANGHAI,
+        ),
+        (cancun_fields(), SpecId::CANCUN),
+    ];
+    for (extra, expected) in cases {
+        let simulator = fork_at(1, header(1_700_000_000, extra.clone()))
+            .await
+            .unwrap();
+        assert_eq!(simulator.spec_id(), expected, "{extra}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pre_london_spec_follows_mainnet_heights() {
+    let cases = [
+        (12_244_000, SpecId::BERLIN),
+        (12_243_999, SpecId::MUIR_GLACIER),
+        (9_069_000, SpecId::ISTANBUL),
+        (7_280_000, SpecId::PETERSBURG),
+        (4_369_999, SpecId::SPURIOUS_DRAGON),
+        (1_150_000, SpecId::HOMESTEAD),
+        (1, SpecId::FRONTIER),
+    ];
+    for (number, expected) in cases {
+        let simulator = fork_at(1, header_at(number, 1_500_000_000, json!({})))
+            .await
+            .unwrap();
+        assert_eq!(simulator.spec_id(), expected, "{number}");
+    }
+    // 其他链没有激活高度，无法判断 London 之前的硬分叉
+    assert!(matches!(
+        fork_at(17000, header(1_700_000_000, json!({}))).await,
+        Err(SimulationError::UnsupportedFork(_))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_env_matches_header() {
+    let simulator = fork_at(1, header(1_700_000_000, cancun_fields()))
+        .await
+        .unwrap();
+    let block_env = simulator.block_env();
+    assert_eq!(block_env.number, rU256::from(NUMBER));
+    assert_eq!(block_env.timestamp, rU256::from(1_700_000_000u64));
+    assert_eq!(block_env.gas_limit, rU256::from(30_000_000u64));
+    assert_eq!(block_env.basefee, rU256::from(1_000_000_000u64));
+    assert_eq!(
+        block_env.coinbase,
+        address!("95222290dd7278aa3ddd389cc1e1d165cc4bafe5")
+    );
+    assert_eq!(
+        block_env.prevrandao,
+        Some(b256!(
+            "5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d5d"
+        ))
+    );
+    // 更新系数 3338477 下 excess 20000000 对应 blob 价格 399
+    let cancun_blob = block_env.blob_excess_gas_and_price.clone().unwrap();
+    assert_eq!(cancun_blob.excess_blob_gas, 20_000_000);
+    assert_eq!(cancun_blob.blob_gasprice, 399);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prague_headers_are_rejected() {
+    // revm 18 只实现了 Prague 的草案，任何链上的 Prague 区块都不降级执行
+    let mut prague = cancun_fields();
+    prague["requestsHash"] = json!(format!("0x{}", "e3".repeat(32)));
+    for chain_id in [1, 17000] {
+        let error = fork_at(chain_id, header(1_750_000_000, prague.clone()))
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&error, SimulationError::UnsupportedFork(reason) if reason.contains("Prague")),
+            "{error}"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fork_errors_are_typed() {
+    let options = ForkOptions {
+        fork_block: Some(BlockId::from(NUMBER)),
+        chain_id: Some(1),
+        cache_dir: None,
+    };
+    // 节点请求失败
+    assert!(matches!(
+        ForkSimulator::new(
+            Arc::new(Provider::new(ReplayClient::default())),
+            options.clone()
+        )
+        .await,
+        Err(SimulationError::Rpc(_))
+    ));
+    // 节点上没有该区块
+    let client = ReplayClient::default();
+    client.insert(
+        "eth_getBlockByNumber",
+        json!([format!("{NUMBER:#x}"), false]),
+        Value::Null,
+    );
+    assert!(matches!(
+        ForkSimulator::new(Arc::new(Provider::new(client)), options.clone()).await,
+        Err(SimulationError::BlockNotFound(_))
+    ));
+
+    // 缓存文件损坏
+    let cache_dir = tempfile::tempdir().unwrap();
+    let path = StateCache::path(cache_dir.path(), 1, NUMBER);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "{").unwrap();
+    let options = ForkOptions {
+        cache_dir: Some(cache_dir.path().to_path_buf()),
+        ..options
+    };
+    let error = ForkSimulator::new(Arc::new(Provider::new(ReplayClient::default())), options)
+        .await
+        .err()
+        .unwrap();
+    assert!(
+        matches!(&error, SimulationError::Cache { path: cache, .. } if *cache == path),
+        "{error}"
+    );
+}
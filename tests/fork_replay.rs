@@ -0,0 +1,67 @@
+mod common;
+
+use common::*;
+use revm::primitives::U256 as rU256;
+use revm_example::{replay::ReplayClient, ForkOptions};
+use revm_primitives::Address;
+
+const RESERVE0: u128 = 0x3aa5712d4e77e453b6c;
+const RESERVE1: u128 = 0x1d11899c5178;
+const BLOCK_TIMESTAMP_LAST: u32 = 0x64ca691b;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_reserves_from_replayed_rpc() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    assert_eq!(simulator.block_number(), FORK_BLOCK);
+    assert_eq!(simulator.chain_id(), 1);
+
+    let (reserve0, reserve1, ts): (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    assert_eq!(
+        (reserve0, reserve1, ts),
+        (RESERVE0, RESERVE1, BLOCK_TIMESTAMP_LAST)
+    );
+
+    let fetched = simulator.fetched();
+    assert!(fetched.accounts.contains_key(&pool_address()));
+    assert_eq!(fetched.storage[&pool_address()].len(), 1);
+    assert!(fetched.storage[&pool_address()].contains_key(&rU256::from(8)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unrecorded_request_fails_without_network() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let unknown = Address::repeat_byte(0x42);
+    assert!(simulator.load_account(unknown).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn state_cache_replays_offline() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let options = ForkOptions {
+        cache_dir: Some(cache_dir.path().to_path_buf()),
+        ..fork_options()
+    };
+
+    let mut simulator = replay_simulator(replay_client(), options.clone()).await;
+    let online: (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    let path = simulator.save_cache().unwrap().unwrap();
+    assert!(path.ends_with(format!("1/{FORK_BLOCK}.json")));
+
+    // 第二次运行没有任何录制，所有数据必须来自磁盘缓存
+    let offline_client = ReplayClient::default();
+    let options = ForkOptions {
+        chain_id: Some(1),
+        ..options
+    };
+    let mut simulator = replay_simulator(offline_client.clone(), options).await;
+    let offline: (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    assert_eq!(online, offline);
+    assert!(offline_client.requests().is_empty());
+    assert_eq!(simulator.cache_db().db.network_requests(), 0);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::*;
+use revm::primitives::U256 as rU256;
+use revm_primitives::Address;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prefetched_state_runs_from_memory() {
+    let client = replay_client();
+    let mut simulator = replay_simulator(client.clone(), fork_options()).await;
+    let miner = simulator.block_env().coinbase;
+
+    let record = simulator
+        .prefetch(&[Address::ZERO, miner], &[(pool_address(), rU256::from(8))])
+        .await
+        .unwrap();
+    // 存储槽所属的池子账户也会被拉取
+    assert_eq!(record.accounts.len(), 3);
+    assert_eq!(record.storage_len(), 1);
+
+    let requests = client.requests().len();
+    let _: (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    assert_eq!(client.requests().len(), requests);
+    assert_eq!(simulator.cache_db().db.network_requests(), 0);
+    assert!(simulator.fetched().accounts.contains_key(&miner));
+
+    // 已经在内存中的数据不会重复拉取
+    let record = simulator
+        .prefetch(&[pool_address()], &[(pool_address(), rU256::from(8))])
+        .await
+        .unwrap();
+    assert!(record.accounts.is_empty());
+    assert_eq!(record.storage_len(), 0);
+}
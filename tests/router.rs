@@ -0,0 +1,152 @@
+mod common;
+
+use common::{mock_v2::*, *};
+use revm::primitives::{Address, U256 as rU256};
+use revm_example::{
+    uniswap_v2::{get_amount_in, get_amount_out},
+    RouterHop, RouterQuote, RouterSwap, SimulationError,
+};
+
+const RESERVES_AB: (u128, u128) = (5_000_000_000_000_000_000_000, 12_000_000_000_000);
+const RESERVES_BC: (u128, u128) = (8_000_000_000_000, 7_900_000_000_000_000_000_000_000);
+
+#[test]
+fn quote_splits_amounts_into_hops() {
+    let (weth, usdt, dai) = (
+        Address::repeat_byte(1),
+        Address::repeat_byte(2),
+        Address::repeat_byte(3),
+    );
+    let quote = RouterQuote {
+        path: vec![weth, usdt, dai],
+        amounts: vec![rU256::from(10), rU256::from(20), rU256::from(30)],
+        gas_used: 0,
+        hop_gas_used: vec![40_000],
+    };
+    assert_eq!(
+        quote.hops(),
+        vec![
+            RouterHop {
+                token_in: weth,
+                token_out: usdt,
+                amount_in: rU256::from(10),
+                amount_out: rU256::from(20),
+                gas_used: Some(40_000),
+            },
+            RouterHop {
+                token_in: usdt,
+                token_out: dai,
+                amount_in: rU256::from(20),
+                amount_out: rU256::from(30),
+                gas_used: None,
+            },
+        ]
+    );
+    assert_eq!(quote.amount_in(), rU256::from(10));
+    assert_eq!(quote.amount_out(), rU256::from(30));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn multi_hop_quotes_follow_library_formula() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    deploy_v2(&mut simulator, RESERVES_AB, RESERVES_BC);
+    let path = [TOKEN_A, TOKEN_B, TOKEN_C];
+
+    let amount_in = ether(2);
+    let quote = simulator
+        .router_amounts_out(ROUTER, amount_in, &path)
+        .unwrap();
+    let middle = get_amount_out(
+        amount_in,
+        rU256::from(RESERVES_AB.0),
+        rU256::from(RESERVES_AB.1),
+    )
+    .unwrap();
+    let out = get_amount_out(
+        middle,
+        rU256::from(RESERVES_BC.0),
+        rU256::from(RESERVES_BC.1),
+    )
+    .unwrap();
+    assert_eq!(quote.amounts, vec![amount_in, middle, out]);
+    assert!(quote.gas_used > 0);
+    assert!(quote.hop_gas_used.is_empty());
+    assert!(quote.hops().iter().all(|hop| hop.gas_used.is_none()));
+
+    // 反向路径按 getAmountIn 从最后一跳往前计算
+    let amount_out = ether(1_000);
+    let quote = simulator
+        .router_amounts_in(ROUTER, amount_out, &[TOKEN_C, TOKEN_B, TOKEN_A])
+        .unwrap();
+    let middle = get_amount_in(
+        amount_out,
+        rU256::from(RESERVES_AB.1),
+        rU256::from(RESERVES_AB.0),
+    )
+    .unwrap();
+    let first = get_amount_in(
+        middle,
+        rU256::from(RESERVES_BC.1),
+        rU256::from(RESERVES_BC.0),
+    )
+    .unwrap();
+    assert_eq!(quote.amounts, vec![first, middle, amount_out]);
+    assert_eq!(quote.amount_in(), first);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn multi_hop_swap_reports_gas_per_hop() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    deploy_v2(&mut simulator, RESERVES_AB, RESERVES_BC);
+    let path = vec![TOKEN_A, TOKEN_B, TOKEN_C];
+    let amount_in = ether(2);
+    let quote = simulator
+        .router_amounts_out(ROUTER, amount_in, &path)
+        .unwrap();
+
+    // 余额和授权槽位自动发现
+    let mut swap = RouterSwap {
+        router: ROUTER,
+        path,
+        amount_in,
+        amount_out_min: quote.amount_out(),
+        from: TRADER,
+        to: TRADER,
+        balance_slot: None,
+        allowance_slot: None,
+        deadline: None,
+    };
+    let executed = simulator.simulate_router_swap(&swap).unwrap();
+    assert_eq!(executed.amounts, quote.amounts);
+    assert_eq!(executed.hop_gas_used.len(), 2);
+    assert!(executed.hop_gas_used.iter().all(|gas| *gas > 0));
+    assert!(executed.hop_gas_used.iter().sum::<u64>() < executed.gas_used);
+    assert_eq!(
+        executed
+            .hops()
+            .iter()
+            .map(|hop| hop.gas_used)
+            .collect::<Vec<_>>(),
+        executed
+            .hop_gas_used
+            .iter()
+            .copied()
+            .map(Some)
+            .collect::<Vec<_>>()
+    );
+
+    // 模拟不改变分叉状态
+    assert_eq!(token_balance(&mut simulator, TOKEN_A, TRADER), rU256::ZERO);
+    assert_eq!(token_balance(&mut simulator, TOKEN_C, TRADER), rU256::ZERO);
+    assert_eq!(
+        token_balance(&mut simulator, TOKEN_B, PAIR_AB),
+        rU256::from(RESERVES_AB.1)
+    );
+
+    // 达不到 amountOutMin 时 Router 回滚
+    swap.amount_out_min = quote.amount_out() + rU256::from(1);
+    assert!(matches!(
+        simulator.simulate_router_swap(&swap),
+        Err(SimulationError::Revert { .. })
+    ));
+}
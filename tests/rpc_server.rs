@@ -0,0 +1,169 @@
+mod common;
+
+use common::*;
+use ethers_core::{
+    k256::ecdsa::SigningKey,
+    types::{
+        transaction::eip2718::TypedTransaction, Bytes as eBytes, Eip1559TransactionRequest,
+        TransactionRequest, H256, U256,
+    },
+    utils::secret_key_to_address,
+};
+use ethers_providers::{Http, Middleware, Provider, RpcError};
+use revm::primitives::{AccountInfo, Bytes, U256 as rU256};
+use revm_example::{
+    utils::{to_ethers_address, to_revm_address},
+    RpcServer,
+};
+
+/// 用测试私钥签名一笔调用计数器的 EIP-1559 交易，优先费为 1 gwei（不超过 `max_fee_per_gas`）
+fn signed_increment(key: &SigningKey, nonce: u64, max_fee_per_gas: rU256) -> eBytes {
+    let max_fee_per_gas = U256(max_fee_per_gas.into_limbs());
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(to_ethers_address(COUNTER))
+        .gas(100_000)
+        .max_fee_per_gas(max_fee_per_gas)
+        .max_priority_fee_per_gas(max_fee_per_gas.min(1_000_000_000u64.into()))
+        .nonce(nonce)
+        .chain_id(1)
+        .into();
+    sign_transaction(key, tx)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serves_fork_to_ethers_provider() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    insert_contract(&mut simulator, REVERTER, Bytes::from_static(&REVERTER_CODE));
+    let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+    let signer = secret_key_to_address(&key);
+    simulator.cache_db_mut().insert_account_info(
+        to_revm_address(signer),
+        AccountInfo {
+            balance: ether(1),
+            ..Default::default()
+        },
+    );
+    let basefee = simulator.block_env().basefee;
+
+    let (addr, server) = RpcServer::new(simulator)
+        .bind(([127, 0, 0, 1], 0).into())
+        .unwrap();
+    tokio::spawn(server);
+    let provider = Provider::<Http>::try_from(format!("http://{addr}")).unwrap();
+    let counter = to_ethers_address(COUNTER);
+
+    assert_eq!(provider.get_chainid().await.unwrap(), 1.into());
+    assert_eq!(
+        provider.get_block_number().await.unwrap(),
+        FORK_BLOCK.into()
+    );
+    assert_eq!(
+        provider.get_balance(signer, None).await.unwrap(),
+        U256(ether(1).into_limbs())
+    );
+    assert_eq!(
+        provider.get_code(counter, None).await.unwrap().to_vec(),
+        COUNTER_CODE
+    );
+    assert_eq!(
+        provider
+            .get_storage_at(counter, H256::zero(), None)
+            .await
+            .unwrap(),
+        H256::zero()
+    );
+
+    let call: TypedTransaction = TransactionRequest::new().to(counter).into();
+    let output = provider.call(&call, None).await.unwrap();
+    assert_eq!(U256::from_big_endian(&output), 1.into());
+    let gas = provider.estimate_gas(&call, None).await.unwrap();
+    assert!(gas > 21_000.into());
+
+    let revert: TypedTransaction = TransactionRequest::new()
+        .to(to_ethers_address(REVERTER))
+        .into();
+    let error = provider.call(&revert, None).await.unwrap_err();
+    let response = error.as_error_response().unwrap();
+    assert_eq!(response.code, 3);
+    assert_eq!(response.message, "execution reverted");
+
+    let snapshot: U256 = provider.request("evm_snapshot", ()).await.unwrap();
+    let pending = provider
+        .send_raw_transaction(signed_increment(&key, 0, basefee * rU256::from(2)))
+        .await
+        .unwrap();
+    let hash = pending.tx_hash();
+    let receipt = provider
+        .get_transaction_receipt(hash)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(receipt.transaction_hash, hash);
+    assert_eq!(receipt.status, Some(1.into()));
+    assert_eq!(receipt.from, signer);
+    assert_eq!(receipt.block_number, Some(FORK_BLOCK.into()));
+    assert_eq!(receipt.logs.len(), 1);
+    assert_eq!(receipt.logs[0].address, counter);
+    assert_eq!(
+        receipt.effective_gas_price,
+        Some(U256(basefee.into_limbs()) + 1_000_000_000u64)
+    );
+    assert_eq!(
+        provider
+            .get_storage_at(counter, H256::zero(), None)
+            .await
+            .unwrap(),
+        H256::from_low_u64_be(1)
+    );
+    assert_eq!(
+        provider.get_transaction_count(signer, None).await.unwrap(),
+        1.into()
+    );
+
+    let reverted: bool = provider.request("evm_revert", [snapshot]).await.unwrap();
+    assert!(reverted);
+    assert_eq!(
+        provider
+            .get_storage_at(counter, H256::zero(), None)
+            .await
+            .unwrap(),
+        H256::zero()
+    );
+    assert!(provider
+        .get_transaction_receipt(hash)
+        .await
+        .unwrap()
+        .is_none());
+
+    // 低于 basefee 或 nonce 不对的交易被拒绝，状态不变
+    for raw in [
+        signed_increment(&key, 0, basefee - rU256::from(1)),
+        signed_increment(&key, 1, basefee * rU256::from(2)),
+    ] {
+        let error = provider.send_raw_transaction(raw).await.unwrap_err();
+        assert_eq!(error.as_error_response().unwrap().code, -32000);
+    }
+    assert_eq!(
+        provider.get_transaction_count(signer, None).await.unwrap(),
+        0.into()
+    );
+
+    // gas 超出 u64 的交易按参数错误返回，服务继续处理后续请求
+    let oversized: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(counter)
+        .gas(U256::from(u64::MAX) + 1)
+        .chain_id(1)
+        .into();
+    let error = provider
+        .send_raw_transaction(sign_transaction(&key, oversized))
+        .await
+        .unwrap_err();
+    assert_eq!(error.as_error_response().unwrap().code, -32602);
+
+    let error = provider
+        .request::<_, U256>("eth_mining", ())
+        .await
+        .unwrap_err();
+    assert_eq!(error.as_error_response().unwrap().code, -32601);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::*;
+use ethers_core::abi::Token;
+use revm_example::{signature::format_token, Signature};
+
+#[test]
+fn shorthand_and_full_signatures_are_equivalent() {
+    let short = Signature::parse("getReserves()(uint112,uint112,uint32)").unwrap();
+    let full = Signature::parse(
+        "function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)",
+    )
+    .unwrap();
+    assert_eq!(short.function().signature(), full.function().signature());
+    assert_eq!(short.function().outputs.len(), 3);
+}
+
+#[test]
+fn encodes_string_arguments() {
+    let signature = Signature::parse("transfer(address,uint256)(bool)").unwrap();
+    let calldata = signature
+        .encode(&[
+            "0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852".to_string(),
+            "1000".to_string(),
+        ])
+        .unwrap();
+    assert_eq!(&calldata[..4], &[0xa9, 0x05, 0x9c, 0xbb]);
+    assert_eq!(calldata[calldata.len() - 1], 0xe8);
+    assert!(signature.encode(&["1".to_string()]).is_err());
+
+    let array = Signature::parse("getAmountsOut(uint256,address[])(uint256[])").unwrap();
+    let calldata = array
+        .encode(&[
+            "0x10".to_string(),
+            "[0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852,0x0d4a11d5EEaaC28EC3F61d100daF4d40471f1852]"
+                .to_string(),
+        ])
+        .unwrap();
+    // selector + amountIn + offset + length + 2 addresses
+    assert_eq!(calldata.len(), 4 + 32 * 5);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn calls_fork_with_shorthand_signature() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let signature = Signature::parse("getReserves()(uint112,uint112,uint32)").unwrap();
+    let output = simulator
+        .call(pool_address(), signature.encode(&[]).unwrap())
+        .unwrap();
+    let tokens = signature.decode(&output).unwrap();
+    let formatted: Vec<_> = tokens.iter().map(format_token).collect();
+    assert_eq!(
+        formatted,
+        [
+            0x3aa5712d4e77e453b6c_u128.to_string(),
+            0x1d11899c5178_u64.to_string(),
+            0x64ca691b_u32.to_string(),
+        ]
+    );
+    assert_eq!(
+        format_token(&Token::Int(ethers_core::types::U256::MAX)),
+        "-1"
+    );
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::*;
+use revm::primitives::{AccountInfo, Bytes, HashMap, TransactTo, TxEnv, U256 as rU256};
+use revm_example::PairReserves;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn revert_restores_overridden_state() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let original = simulator.pair_reserves(pool_address()).unwrap();
+    let _: (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+
+    let base = simulator.snapshot();
+    let first = PairReserves {
+        reserve0: 1,
+        reserve1: 2,
+        block_timestamp_last: 3,
+    };
+    simulator.set_pair_reserves(pool_address(), first).unwrap();
+    let nested = simulator.snapshot();
+    simulator
+        .set_pair_reserves(
+            pool_address(),
+            PairReserves {
+                reserve0: 4,
+                ..first
+            },
+        )
+        .unwrap();
+
+    assert!(simulator.revert_to(nested));
+    assert_eq!(simulator.pair_reserves(pool_address()).unwrap(), first);
+    // 回滚后快照被删除
+    assert!(!simulator.revert_to(nested));
+
+    assert!(simulator.revert_to(base));
+    assert_eq!(simulator.pair_reserves(pool_address()).unwrap(), original);
+    // 回滚后读取链上数据仍然命中内存
+    let requests = simulator.cache_db().db.network_requests();
+    let _: (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    assert_eq!(simulator.cache_db().db.network_requests(), requests);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reverting_drops_later_snapshots() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let first = simulator.snapshot();
+    let second = simulator.snapshot();
+    assert!(simulator.revert_to(first));
+    assert!(!simulator.revert_to(second));
+    assert!(!simulator.revert_to(42));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn revert_undoes_committed_transactions() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    simulator.insert_account_info(SENDER, AccountInfo::from_balance(ether(1)));
+    let increment = TxEnv {
+        caller: SENDER,
+        transact_to: TransactTo::Call(COUNTER),
+        gas_limit: 100_000,
+        gas_price: rU256::from(1_000_000_000u64),
+        ..Default::default()
+    };
+    simulator.commit(increment.clone()).unwrap();
+    // 录制中没有新合约的地址，先放置一个空账户
+    simulator.insert_account_info(SENDER.create(2), AccountInfo::default());
+
+    let snapshot = simulator.snapshot();
+    simulator.commit(increment.clone()).unwrap();
+    // 部署一个返回 COUNTER_CODE 的合约
+    let mut init_code = hex::decode("6018600c60003960186000f3").unwrap();
+    init_code.extend_from_slice(&COUNTER_CODE);
+    let created = simulator
+        .commit(TxEnv {
+            transact_to: TransactTo::Create,
+            data: init_code.into(),
+            ..increment.clone()
+        })
+        .unwrap()
+        .contract_address
+        .unwrap();
+    assert_eq!(created, SENDER.create(2));
+    simulator.commit(increment.clone()).unwrap();
+    simulator
+        .replace_account_storage(COUNTER, HashMap::default())
+        .unwrap();
+    simulator.insert_account_info(REVERTER, AccountInfo::from_balance(ether(2)));
+    assert_eq!(
+        simulator
+            .load_account(created)
+            .unwrap()
+            .code
+            .unwrap()
+            .original_bytes(),
+        Bytes::from_static(&COUNTER_CODE)
+    );
+    assert_eq!(simulator.receipts().len(), 4);
+
+    assert!(simulator.revert_to(snapshot));
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::ZERO).unwrap(),
+        rU256::from(1)
+    );
+    let sender = simulator.load_account(SENDER).unwrap();
+    assert_eq!(sender.nonce, 1);
+    assert_eq!(
+        sender.balance,
+        ether(1) - increment.gas_price * rU256::from(simulator.receipts()[0].gas_used)
+    );
+    assert!(simulator.load_account(created).unwrap().is_empty());
+    assert_eq!(simulator.receipts().len(), 1);
+
+    // 回滚后继续提交，状态从快照处接着演进
+    let receipt = simulator.commit(increment).unwrap();
+    assert_eq!(receipt.index, 1);
+    assert_eq!(
+        receipt.output(),
+        Bytes::from(rU256::from(2).to_be_bytes_vec())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn revert_restores_direct_cache_db_writes() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    let snapshot = simulator.snapshot();
+    simulator
+        .insert_account_storage(COUNTER, rU256::ZERO, rU256::from(7))
+        .unwrap();
+    // 直接修改 CacheDB 时同样可以回滚
+    simulator
+        .cache_db_mut()
+        .insert_account_storage(COUNTER, rU256::from(1), rU256::from(8))
+        .unwrap();
+    simulator.cache_db_mut().accounts.remove(&COUNTER);
+    assert!(simulator.revert_to(snapshot));
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::ZERO).unwrap(),
+        rU256::ZERO
+    );
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::from(1)).unwrap(),
+        rU256::ZERO
+    );
+    assert_eq!(
+        simulator
+            .load_account(COUNTER)
+            .unwrap()
+            .code
+            .unwrap()
+            .original_bytes(),
+        Bytes::from_static(&COUNTER_CODE)
+    );
+}
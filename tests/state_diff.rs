@@ -0,0 +1,128 @@
+mod common;
+
+use common::*;
+use hex_literal::hex;
+use revm::primitives::{AccountInfo, Bytes, TransactTo, TxEnv, U256 as rU256};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn state_diff_reports_changed_accounts() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    let db = simulator.cache_db_mut();
+    db.insert_account_storage(COUNTER, rU256::ZERO, rU256::from(5))
+        .unwrap();
+    // 只读的存储槽不应出现在差异中
+    db.insert_account_storage(COUNTER, rU256::from(1), rU256::from(7))
+        .unwrap();
+    db.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: ether(1),
+            ..Default::default()
+        },
+    );
+
+    let gas_price = simulator.block_env().basefee;
+    let (result, diff) = simulator
+        .simulate_diff(TxEnv {
+            caller: SENDER,
+            transact_to: TransactTo::Call(COUNTER),
+            value: rU256::from(100),
+            gas_limit: 100_000,
+            gas_price,
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(result.is_success());
+    let fee = gas_price * rU256::from(result.gas_used());
+
+    // 价格等于 basefee 时出块地址的余额不变，不出现在差异中
+    assert_eq!(
+        diff.addresses().into_iter().collect::<Vec<_>>(),
+        [COUNTER, SENDER]
+    );
+
+    let sender_pre = &diff.pre[&SENDER];
+    assert_eq!(sender_pre.balance, Some(ether(1)));
+    assert_eq!(sender_pre.nonce, None);
+    assert_eq!(sender_pre.code, None);
+    let sender_post = &diff.post[&SENDER];
+    assert_eq!(sender_post.balance, Some(ether(1) - rU256::from(100) - fee));
+    assert_eq!(sender_post.nonce, Some(1));
+
+    let counter_pre = &diff.pre[&COUNTER];
+    assert_eq!(counter_pre.balance, Some(rU256::ZERO));
+    assert_eq!(counter_pre.nonce, Some(1));
+    assert_eq!(counter_pre.code, Some(Bytes::from_static(&COUNTER_CODE)));
+    assert_eq!(counter_pre.storage, [(slot(0), slot(5))].into());
+    let counter_post = &diff.post[&COUNTER];
+    assert_eq!(counter_post.balance, Some(rU256::from(100)));
+    assert_eq!(counter_post.nonce, None);
+    assert_eq!(counter_post.code, None);
+    assert_eq!(counter_post.storage, [(slot(0), slot(6))].into());
+
+    let json = serde_json::to_value(&diff).unwrap();
+    let counter = COUNTER.to_string().to_lowercase();
+    assert_eq!(json["pre"][&counter]["balance"], "0x0");
+    assert_eq!(json["pre"][&counter]["nonce"], 1);
+    assert_eq!(
+        json["post"][&counter]["storage"][slot(0).to_string()],
+        slot(6).to_string()
+    );
+    assert!(json["post"][&counter].get("nonce").is_none());
+    assert!(json["pre"][SENDER.to_string().to_lowercase()]
+        .get("nonce")
+        .is_none());
+
+    let text = diff.to_string();
+    assert!(
+        text.contains(&format!(
+            "{COUNTER}\n  balance: 0 → 100\n  storage {}: 0x5 → 0x6\n",
+            slot(0)
+        )),
+        "{text}"
+    );
+    assert!(text.contains("  nonce: 0 → 1\n"), "{text}");
+
+    // 状态没有写回
+    assert_eq!(
+        simulator.load_storage(COUNTER, rU256::ZERO).unwrap(),
+        rU256::from(5)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn state_diff_omits_created_contract_from_pre() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let deployed = SENDER.create(0);
+    let db = simulator.cache_db_mut();
+    db.insert_account_info(SENDER, AccountInfo::default());
+    db.insert_account_info(deployed, AccountInfo::default());
+    // 初始化代码写入槽位 1，再返回后面的计数器代码
+    let init_code = [
+        &hex!("60016001556018601160003960186000f3")[..],
+        &COUNTER_CODE,
+    ]
+    .concat();
+
+    let (result, diff) = simulator
+        .simulate_diff(TxEnv {
+            caller: SENDER,
+            transact_to: TransactTo::Create,
+            data: init_code.into(),
+            gas_limit: 200_000,
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(result.is_success(), "{result:?}");
+
+    assert!(!diff.pre.contains_key(&deployed));
+    let post = &diff.post[&deployed];
+    assert_eq!(post.nonce, Some(1));
+    assert_eq!(post.code, Some(Bytes::from_static(&COUNTER_CODE)));
+    assert_eq!(post.storage, [(slot(1), slot(1))].into());
+    assert_eq!(diff.post[&SENDER].nonce, Some(1));
+    assert!(diff.to_string().contains(&format!(
+        "{deployed} (created)\n  nonce: 0 → 1\n  code: 0 bytes → 24 bytes\n"
+    )));
+}
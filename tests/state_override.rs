@@ -0,0 +1,175 @@
+mod common;
+
+use common::*;
+use revm::{
+    primitives::{AccountInfo, Bytes, TransactTo, TxEnv, U256 as rU256},
+    Database,
+};
+use revm_example::{error::SimulationError, RpcServer, StateOverride};
+use serde_json::json;
+
+fn call_counter() -> TxEnv {
+    TxEnv {
+        caller: SENDER,
+        transact_to: TransactTo::Call(COUNTER),
+        ..Default::default()
+    }
+}
+
+fn overrides(value: serde_json::Value) -> StateOverride {
+    serde_json::from_value(value).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn overrides_apply_to_a_single_simulation() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    // 空合约：代码全部来自覆盖
+    insert_contract(&mut simulator, COUNTER, Bytes::new());
+    simulator
+        .cache_db_mut()
+        .insert_account_info(SENDER, AccountInfo::default());
+
+    let overrides = overrides(json!({
+        COUNTER.to_string(): {
+            "code": format!("0x{}", hex::encode(COUNTER_CODE)),
+            "stateDiff": { slot(0).to_string(): slot(41).to_string() },
+        },
+        SENDER.to_string(): { "balance": "0xde0b6b3a7640000", "nonce": "0x7" },
+    }));
+    let outcome = simulator
+        .simulate_with_override(
+            TxEnv {
+                value: rU256::from(100),
+                ..call_counter()
+            },
+            &overrides,
+        )
+        .unwrap();
+    assert!(outcome.result.is_success());
+    assert_eq!(outcome.result.output().unwrap()[..], slot(42)[..]);
+    assert_eq!(outcome.state[&SENDER].info.nonce, 8);
+
+    // 执行结束后覆盖被撤销
+    let db = simulator.cache_db_mut();
+    let sender = db.basic(SENDER).unwrap().unwrap();
+    assert_eq!((sender.balance, sender.nonce), (rU256::ZERO, 0));
+    let counter = db.basic(COUNTER).unwrap().unwrap();
+    assert!(counter.code.unwrap().is_empty());
+    assert_eq!(db.storage(COUNTER, rU256::ZERO).unwrap(), rU256::ZERO);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn state_replaces_all_storage_and_state_diff_patches() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    let db = simulator.cache_db_mut();
+    db.insert_account_storage(COUNTER, rU256::ZERO, rU256::from(5))
+        .unwrap();
+    db.insert_account_storage(COUNTER, rU256::from(1), rU256::from(7))
+        .unwrap();
+
+    // state：未列出的槽位清零
+    simulator
+        .apply_state_override(&overrides(json!({
+            COUNTER.to_string(): { "state": { slot(2).to_string(): slot(9).to_string() } },
+        })))
+        .unwrap();
+    let db = simulator.cache_db_mut();
+    assert_eq!(db.storage(COUNTER, rU256::ZERO).unwrap(), rU256::ZERO);
+    assert_eq!(db.storage(COUNTER, rU256::from(1)).unwrap(), rU256::ZERO);
+    assert_eq!(db.storage(COUNTER, rU256::from(2)).unwrap(), rU256::from(9));
+    // 代码、余额等没有覆盖的字段保持不变
+    let counter = db.basic(COUNTER).unwrap().unwrap();
+    assert_eq!(counter.nonce, 1);
+    assert_eq!(
+        counter.code.unwrap().original_bytes(),
+        Bytes::from_static(&COUNTER_CODE)
+    );
+
+    // stateDiff：只修改列出的槽位
+    simulator
+        .apply_state_override(&overrides(json!({
+            COUNTER.to_string(): { "stateDiff": { slot(0).to_string(): slot(3).to_string() } },
+        })))
+        .unwrap();
+    let db = simulator.cache_db_mut();
+    assert_eq!(db.storage(COUNTER, rU256::ZERO).unwrap(), rU256::from(3));
+    assert_eq!(db.storage(COUNTER, rU256::from(2)).unwrap(), rU256::from(9));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_overrides_are_rejected() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    simulator
+        .cache_db_mut()
+        .insert_account_info(SENDER, AccountInfo::default());
+
+    // 同时设置 state 和 stateDiff 时不做任何修改
+    let error = simulator
+        .apply_state_override(&overrides(json!({
+            SENDER.to_string(): { "balance": "0x1" },
+            COUNTER.to_string(): {
+                "state": { slot(0).to_string(): slot(1).to_string() },
+                "stateDiff": { slot(0).to_string(): slot(2).to_string() },
+            },
+        })))
+        .unwrap_err();
+    assert!(matches!(error, SimulationError::InvalidStateOverride(_)));
+    let sender = simulator.cache_db_mut().basic(SENDER).unwrap().unwrap();
+    assert_eq!(sender.balance, rU256::ZERO);
+
+    // 未知字段按 Geth 的做法拒绝
+    let unknown = json!({ COUNTER.to_string(): { "storage": {} } });
+    assert!(serde_json::from_value::<StateOverride>(unknown).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rpc_eth_call_accepts_state_overrides() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    simulator
+        .cache_db_mut()
+        .insert_account_info(SENDER, AccountInfo::default());
+    let server = RpcServer::new(simulator);
+
+    let call = json!({ "from": SENDER, "to": COUNTER });
+    let state_override = json!({
+        COUNTER.to_string(): { "stateDiff": { slot(0).to_string(): slot(99).to_string() } },
+    });
+    let response = server
+        .handle(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_call",
+            "params": [call, "latest", state_override],
+        }))
+        .await;
+    assert_eq!(response["result"], json!(Bytes::from(slot(100).to_vec())));
+
+    // 覆盖只作用于这一次调用
+    let response = server
+        .handle(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "eth_call",
+            "params": [call, "latest", null],
+        }))
+        .await;
+    assert_eq!(response["result"], json!(Bytes::from(slot(1).to_vec())));
+
+    // 没有覆盖时直接执行，不占用快照
+    let response = server
+        .handle(json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "eth_estimateGas",
+            "params": [call],
+        }))
+        .await;
+    assert!(response["result"].is_string());
+    let response = server
+        .handle(json!({ "jsonrpc": "2.0", "id": 4, "method": "evm_snapshot", "params": [] }))
+        .await;
+    assert_eq!(response["result"], json!("0x1"));
+}
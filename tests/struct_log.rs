@@ -0,0 +1,155 @@
+mod common;
+
+use common::*;
+use revm::primitives::{address, Address, Bytes, TransactTo, TxEnv, U256 as rU256};
+use revm_example::StructLogConfig;
+
+fn word(value: u64) -> String {
+    format!("{value:064x}")
+}
+
+const FAULTY: Address = address!("00000000000000000000000000000000000fa017");
+
+fn call(gas_limit: u64) -> TxEnv {
+    call_to(COUNTER, gas_limit)
+}
+
+fn call_to(to: Address, gas_limit: u64) -> TxEnv {
+    TxEnv {
+        transact_to: TransactTo::Call(to),
+        gas_limit,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn struct_logs_match_geth_format() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+    simulator
+        .cache_db_mut()
+        .insert_account_storage(COUNTER, rU256::ZERO, rU256::from(5))
+        .unwrap();
+
+    let config = StructLogConfig {
+        enable_memory: true,
+        ..Default::default()
+    };
+    let trace = simulator.trace_struct_logs(call(100_000), config).unwrap();
+
+    let ops: Vec<&str> = trace
+        .struct_logs
+        .iter()
+        .map(|log| log.op.as_str())
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            "PUSH1", "SLOAD", "PUSH1", "ADD", "DUP1", "PUSH1", "SSTORE", "DUP1", "PUSH1", "MSTORE",
+            "PUSH1", "PUSH1", "LOG0", "PUSH1", "PUSH1", "RETURN"
+        ]
+    );
+    assert!(!trace.failed);
+    assert_eq!(trace.return_value, word(6));
+    assert_eq!(
+        trace.gas,
+        21_000
+            + trace
+                .struct_logs
+                .iter()
+                .map(|log| log.gas_cost)
+                .sum::<u64>()
+    );
+
+    let first = &trace.struct_logs[0];
+    assert_eq!((first.pc, first.depth, first.gas_cost), (0, 1, 3));
+    assert_eq!(first.gas, 100_000 - 21_000);
+    assert_eq!(first.stack.as_deref(), Some(&[][..]));
+    assert_eq!(first.storage, None);
+
+    let sload = &trace.struct_logs[1];
+    assert_eq!(sload.gas_cost, 2_100);
+    assert_eq!(sload.gas, first.gas - 3);
+    assert_eq!(sload.stack.as_ref().unwrap(), &["0x0"]);
+    assert_eq!(
+        sload.storage.as_ref().unwrap().get(&word(0)),
+        Some(&word(5))
+    );
+
+    let sstore = &trace.struct_logs[6];
+    assert_eq!(sstore.pc, 9);
+    assert_eq!(sstore.gas_cost, 2_900);
+    assert_eq!(sstore.stack.as_ref().unwrap(), &["0x6", "0x6", "0x0"]);
+    assert_eq!(
+        sstore.storage.as_ref().unwrap().get(&word(0)),
+        Some(&word(6))
+    );
+
+    // 内存反映执行前的状态：MSTORE 之前为空，之后的 LOG0 能看到写入的值
+    assert_eq!(trace.struct_logs[9].memory.as_deref(), Some(&[][..]));
+    assert_eq!(trace.struct_logs[12].memory.as_ref().unwrap(), &[word(6)]);
+
+    let json = serde_json::to_value(&trace).unwrap();
+    assert_eq!(json["returnValue"], word(6));
+    assert_eq!(json["structLogs"][1]["gasCost"], 2_100);
+    assert_eq!(json["structLogs"][1]["storage"][word(0)], word(5));
+    assert!(json["structLogs"][0].get("storage").is_none());
+    assert!(json["structLogs"][0].get("error").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn struct_logs_record_halting_opcode() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, COUNTER, Bytes::from_static(&COUNTER_CODE));
+
+    // 只够 PUSH1，SLOAD 时 gas 不足
+    let trace = simulator
+        .trace_struct_logs(call(21_000 + 100), StructLogConfig::default())
+        .unwrap();
+
+    assert!(trace.failed);
+    assert_eq!(trace.gas, 21_100);
+    assert_eq!(trace.struct_logs.len(), 2);
+    let sload = &trace.struct_logs[1];
+    assert_eq!(sload.op, "SLOAD");
+    assert_eq!(sload.error.as_deref(), Some("out of gas"));
+    assert_eq!(sload.storage, None);
+    assert_eq!(sload.memory, None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn struct_log_errors_match_geth() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    insert_contract(&mut simulator, REVERTER, Bytes::from_static(&REVERTER_CODE));
+
+    let trace = simulator
+        .trace_struct_logs(call_to(REVERTER, 100_000), StructLogConfig::default())
+        .unwrap();
+    assert!(trace.failed);
+    let errors: Vec<_> = trace
+        .struct_logs
+        .iter()
+        .map(|log| (log.op.as_str(), log.error.as_deref()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("PUSH1", None),
+            ("PUSH1", None),
+            ("REVERT", Some("execution reverted"))
+        ]
+    );
+
+    for (code, op, error) in [
+        (&[0x01][..], "ADD", "stack underflow (0 <=> 2)"),
+        (&[0xfe], "INVALID", "invalid opcode: INVALID"),
+        (&[0x60, 0x03, 0x56], "JUMP", "invalid jump destination"),
+    ] {
+        insert_contract(&mut simulator, FAULTY, Bytes::copy_from_slice(code));
+        let trace = simulator
+            .trace_struct_logs(call_to(FAULTY, 100_000), StructLogConfig::default())
+            .unwrap();
+        let last = trace.struct_logs.last().unwrap();
+        assert_eq!((last.op.as_str(), last.error.as_deref()), (op, Some(error)));
+    }
+}
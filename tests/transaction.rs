@@ -0,0 +1,56 @@
+mod common;
+
+use common::*;
+use ethers_core::{
+    k256::ecdsa::SigningKey,
+    types::{transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, U256},
+    utils::secret_key_to_address,
+};
+use revm::primitives::{keccak256, TransactTo, U256 as rU256};
+use revm_example::{
+    transaction::decode_raw_transaction,
+    utils::{to_ethers_address, to_revm_address},
+    SimulationError,
+};
+
+fn increment() -> Eip1559TransactionRequest {
+    Eip1559TransactionRequest::new()
+        .to(to_ethers_address(COUNTER))
+        .gas(100_000)
+        .max_fee_per_gas(2_000_000_000u64)
+        .max_priority_fee_per_gas(1_000_000_000u64)
+        .nonce(3)
+        .chain_id(1)
+}
+
+#[test]
+fn decodes_signed_eip1559_transaction() {
+    let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+    let raw = sign_transaction(&key, increment().into());
+
+    let decoded = decode_raw_transaction(&raw).unwrap();
+    assert_eq!(decoded.hash, keccak256(&raw));
+    let tx_env = decoded.tx_env;
+    assert_eq!(tx_env.caller, to_revm_address(secret_key_to_address(&key)));
+    assert_eq!(tx_env.transact_to, TransactTo::Call(COUNTER));
+    assert_eq!(tx_env.gas_limit, 100_000);
+    assert_eq!(tx_env.gas_price, rU256::from(2_000_000_000u64));
+    assert_eq!(tx_env.gas_priority_fee, Some(rU256::from(1_000_000_000u64)));
+    assert_eq!(tx_env.nonce, Some(3));
+    assert_eq!(tx_env.chain_id, Some(1));
+}
+
+#[test]
+fn oversized_fields_are_rejected() {
+    let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+    // 签名只覆盖 RLP 编码，超出 u64 的 gas 和 nonce 同样能恢复出发送者
+    let oversized = U256::from(u64::MAX) + 1;
+    for tx in [increment().gas(oversized), increment().nonce(oversized)] {
+        let tx: TypedTransaction = tx.into();
+        let raw = sign_transaction(&key, tx);
+        assert!(matches!(
+            decode_raw_transaction(&raw),
+            Err(SimulationError::InvalidRawTransaction(_))
+        ));
+    }
+}
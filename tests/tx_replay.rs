@@ -0,0 +1,267 @@
+mod common;
+
+use common::COUNTER_CODE;
+use ethers_providers::Provider;
+use revm::primitives::{b256, B256, U256 as rU256};
+use revm_example::{
+    replay::ReplayClient, ForkOptions, ForkSimulator, SimulationError, TxReplay, TxReplayOptions,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const COUNTER: &str = "0x00000000000000000000000000000000000c0c0c";
+const ALICE: &str = "0x00000000000000000000000000000000000a11ce";
+const BOB: &str = "0x0000000000000000000000000000000000000b0b";
+const COINBASE: &str = "0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5";
+/// 区块中的第一笔交易，由 ALICE 发送
+const FIRST: B256 = b256!("1111111111111111111111111111111111111111111111111111111111111111");
+/// 被重放的第二笔交易，由 BOB 发送
+const SECOND: B256 = b256!("2222222222222222222222222222222222222222222222222222222222222222");
+/// 槽位 0 已经被第一笔交易从 0 改成 1 时，第二笔交易的 gas 消耗
+const SECOND_GAS_USED: u64 = 26_670;
+
+fn block(number: u64, transactions: Vec<Value>) -> Value {
+    json!({
+        "number": format!("{number:#x}"),
+        "hash": format!("0x{:064x}", number),
+        "parentHash": format!("0x{:064x}", number - 1),
+        "miner": COINBASE,
+        "timestamp": "0x64ca6a73",
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "baseFeePerGas": "0x3b9aca00",
+        "difficulty": "0x0",
+        "mixHash": format!("0x{:064x}", 0x5d),
+        "nonce": "0x0000000000000000",
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "extraData": "0x",
+        "transactions": transactions,
+        "uncles": [],
+        "withdrawalsRoot": format!("0x{:064x}", 0x7e),
+    })
+}
+
+fn transaction(hash: B256, index: u64, from: &str) -> Value {
+    json!({
+        "hash": hash,
+        "nonce": "0x0",
+        "blockHash": format!("0x{:064x}", 0x100),
+        "blockNumber": "0x100",
+        "transactionIndex": format!("{index:#x}"),
+        "from": from,
+        "to": COUNTER,
+        "value": "0x0",
+        "gasPrice": "0x3b9aca00",
+        "maxFeePerGas": "0x77359400",
+        "maxPriorityFeePerGas": "0x0",
+        "gas": "0x186a0",
+        "input": "0x",
+        "type": "0x2",
+        "chainId": "0x1",
+        "accessList": [],
+        "v": "0x0",
+        "r": "0x1",
+        "s": "0x1",
+    })
+}
+
+fn insert_account(client: &ReplayClient, address: &str, balance: &str, code: &str) {
+    let params = json!([address, "0xff"]);
+    client.insert("eth_getTransactionCount", params.clone(), json!("0x0"));
+    client.insert("eth_getBalance", params.clone(), json!(balance));
+    client.insert("eth_getCode", params, json!(code));
+}
+
+/// 区块 0x100 中 ALICE 和 BOB 先后调用计数器，槽位 0 在父区块上为 0
+fn client() -> ReplayClient {
+    let client = ReplayClient::default();
+    let first = transaction(FIRST, 0, ALICE);
+    let second = transaction(SECOND, 1, BOB);
+    client.insert("eth_getTransactionByHash", json!([SECOND]), second.clone());
+    client.insert(
+        "eth_getTransactionReceipt",
+        json!([SECOND]),
+        json!({
+            "transactionHash": SECOND,
+            "transactionIndex": "0x1",
+            "blockHash": format!("0x{:064x}", 0x100),
+            "blockNumber": "0x100",
+            "from": BOB,
+            "to": COUNTER,
+            "cumulativeGasUsed": "0x11328",
+            "gasUsed": format!("{SECOND_GAS_USED:#x}"),
+            "contractAddress": null,
+            "logs": [{
+                "address": COUNTER,
+                "topics": [],
+                "data": format!("0x{:064x}", 2),
+            }],
+            "status": "0x1",
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "type": "0x2",
+            "effectiveGasPrice": "0x3b9aca00",
+        }),
+    );
+    client.insert(
+        "eth_getBlockByNumber",
+        json!(["0x100", true]),
+        block(0x100, vec![first, second]),
+    );
+    client.insert(
+        "eth_getBlockByNumber",
+        json!(["0xff", false]),
+        block(0xff, vec![]),
+    );
+    let ether = "0xde0b6b3a7640000";
+    insert_account(&client, ALICE, ether, "0x");
+    insert_account(&client, BOB, ether, "0x");
+    insert_account(&client, COINBASE, "0x0", "0x");
+    insert_account(
+        &client,
+        COUNTER,
+        "0x0",
+        &format!("0x{}", hex::encode(COUNTER_CODE)),
+    );
+    client.insert(
+        "eth_getStorageAt",
+        json!([COUNTER, "0x0", "0xff"]),
+        json!(format!("0x{:064x}", 0)),
+    );
+    client
+}
+
+async fn replay(replay_preceding: bool) -> TxReplay {
+    let options = TxReplayOptions {
+        replay_preceding,
+        chain_id: Some(1),
+        ..Default::default()
+    };
+    let (simulator, replay) =
+        ForkSimulator::replay_tx(Arc::new(Provider::new(client())), SECOND, options)
+            .await
+            .unwrap();
+    assert_eq!(simulator.block_number(), 0xff);
+    assert_eq!(simulator.block_env().number, rU256::from(0x100));
+    replay
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_with_preceding_txs_matches_receipt() {
+    let replay = replay(true).await;
+
+    assert_eq!(replay.block_number, 0x100);
+    assert_eq!(replay.index, 1);
+    assert_eq!(replay.preceding, 1);
+    assert_eq!(replay.gas_used(), SECOND_GAS_USED);
+    assert!(replay.matches(), "{:?}", replay.mismatches());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_without_preceding_txs_reports_mismatches() {
+    let replay = replay(false).await;
+
+    assert_eq!(replay.preceding, 0);
+    assert!(replay.success());
+    // 槽位 0 仍是父区块上的 0，SSTORE 从零写入非零值，计数结果也不同
+    assert_eq!(replay.gas_used(), SECOND_GAS_USED - 2_900 + 20_000);
+    let mismatches = replay.mismatches();
+    assert_eq!(mismatches.len(), 2, "{mismatches:?}");
+    assert!(mismatches[0].contains("gas"));
+    assert!(mismatches[1].contains("第 0 条日志"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_cache_keeps_parent_header() {
+    let cache_dir = tempfile::tempdir().unwrap();
+    let options = TxReplayOptions {
+        replay_preceding: true,
+        chain_id: Some(1),
+        cache_dir: Some(cache_dir.path().to_path_buf()),
+    };
+    let (simulator, _) =
+        ForkSimulator::replay_tx(Arc::new(Provider::new(client())), SECOND, options)
+            .await
+            .unwrap();
+    simulator.save_cache().unwrap().unwrap();
+
+    // 直接在父区块上分叉得到的区块环境
+    let fork_options = |cache_dir: Option<_>| ForkOptions {
+        fork_block: Some(0xffu64.into()),
+        chain_id: Some(1),
+        cache_dir,
+    };
+    let parent = ForkSimulator::new(Arc::new(Provider::new(client())), fork_options(None))
+        .await
+        .unwrap();
+    assert_eq!(parent.block_env().number, rU256::from(0xff));
+
+    // 从缓存重新打开父区块，不访问节点，区块头仍是父区块的
+    let reopened = ForkSimulator::new(
+        Arc::new(Provider::new(ReplayClient::default())),
+        fork_options(Some(cache_dir.path().to_path_buf())),
+    )
+    .await
+    .unwrap();
+    assert_eq!(reopened.block_number(), 0xff);
+    assert_eq!(reopened.block_env(), parent.block_env());
+    assert_eq!(reopened.spec_id(), parent.spec_id());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn eip7702_transactions_are_rejected() {
+    let options = TxReplayOptions {
+        replay_preceding: true,
+        chain_id: Some(1),
+        ..Default::default()
+    };
+    let mut delegation = transaction(FIRST, 0, ALICE);
+    delegation["type"] = json!("0x4");
+    delegation["authorizationList"] = json!([]);
+
+    // 前序交易带有授权列表时，不会被当作普通调用执行
+    let delegating_block = client();
+    delegating_block.insert(
+        "eth_getBlockByNumber",
+        json!(["0x100", true]),
+        block(0x100, vec![delegation, transaction(SECOND, 1, BOB)]),
+    );
+    let error = ForkSimulator::replay_tx(
+        Arc::new(Provider::new(delegating_block)),
+        SECOND,
+        options.clone(),
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(
+        matches!(
+            &error,
+            SimulationError::PrecedingTransaction { hash, source }
+                if *hash == FIRST
+                    && matches!(**source, SimulationError::UnsupportedTransactionType(4))
+        ),
+        "{error}"
+    );
+
+    // 被重放的交易本身
+    let mut target = transaction(SECOND, 1, BOB);
+    target["type"] = json!("0x4");
+    target["authorizationList"] = json!([]);
+    let delegating_tx = client();
+    delegating_tx.insert("eth_getTransactionByHash", json!([SECOND]), target);
+    let error = ForkSimulator::replay_tx(
+        Arc::new(Provider::new(delegating_tx)),
+        SECOND,
+        TxReplayOptions {
+            replay_preceding: false,
+            ..options
+        },
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(
+        matches!(error, SimulationError::UnsupportedTransactionType(4)),
+        "{error}"
+    );
+}
@@ -0,0 +1,45 @@
+use revm::primitives::U256 as rU256;
+use revm_example::uniswap_v2::{get_amount_in, get_amount_out};
+
+// 区块 17830000 时 WETH-USDT 池的储备量
+const RESERVE_WETH: u128 = 0x3aa5712d4e77e453b6c;
+const RESERVE_USDT: u128 = 0x1d11899c5178;
+
+#[test]
+fn amount_out_matches_library_formula() {
+    let amount_out = get_amount_out(
+        rU256::from(10u128.pow(18)),
+        rU256::from(RESERVE_WETH),
+        rU256::from(RESERVE_USDT),
+    );
+    assert_eq!(amount_out, Some(rU256::from(1_840_825_701u64)));
+    assert_eq!(
+        get_amount_out(rU256::ZERO, rU256::from(1), rU256::from(1)),
+        Some(rU256::ZERO)
+    );
+}
+
+#[test]
+fn amount_in_is_inverse_of_amount_out() {
+    let (reserve_in, reserve_out) = (rU256::from(RESERVE_WETH), rU256::from(RESERVE_USDT));
+    let wanted = rU256::from(1_000_000_000u64);
+    let amount_in = get_amount_in(wanted, reserve_in, reserve_out).unwrap();
+    assert_eq!(amount_in, rU256::from(543_220_192_372_469_146u128));
+    assert!(get_amount_out(amount_in, reserve_in, reserve_out).unwrap() >= wanted);
+    assert!(get_amount_out(amount_in - rU256::from(1), reserve_in, reserve_out).unwrap() < wanted);
+    assert_eq!(get_amount_in(reserve_out, reserve_in, reserve_out), None);
+}
+
+#[test]
+fn overflow_is_reported() {
+    let reserve = rU256::from(RESERVE_WETH);
+    assert_eq!(get_amount_out(rU256::MAX, reserve, reserve), None);
+    assert_eq!(
+        get_amount_out(rU256::MAX / rU256::from(997), reserve, reserve),
+        None
+    );
+    assert_eq!(
+        get_amount_in(rU256::MAX - rU256::from(1), rU256::MAX, rU256::MAX),
+        None
+    );
+}
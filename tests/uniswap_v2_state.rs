@@ -0,0 +1,90 @@
+mod common;
+
+use common::*;
+use revm::primitives::U256 as rU256;
+use revm_example::{
+    uniswap_v2::{RESERVES_SLOT, TOKEN0_SLOT},
+    PairReserves, UniswapV2PairState,
+};
+use revm_primitives::{Address, HashMap};
+use std::{convert::Infallible, str::FromStr};
+
+/// main.rs 注释中记录的 WETH-USDT 池槽位 8 的值
+const RESERVES_SLOT_VALUE: &str =
+    "0x64ca691b00000000000000001d11899c51780000000003aa5712d4e77e453b6c";
+
+#[test]
+fn decodes_documented_reserves_slot() {
+    let value = rU256::from_str(RESERVES_SLOT_VALUE).unwrap();
+    let reserves = PairReserves::from_slot(value);
+    assert_eq!(reserves.reserve0, 0x3aa5712d4e77e453b6c);
+    assert_eq!(reserves.reserve1, 0x1d11899c5178);
+    assert_eq!(reserves.block_timestamp_last, 0x64ca691b);
+    assert_eq!(reserves.to_slot().unwrap(), value);
+}
+
+#[test]
+fn rejects_reserves_wider_than_uint112() {
+    let reserves = PairReserves {
+        reserve0: 1 << 112,
+        ..Default::default()
+    };
+    assert!(reserves.to_slot().is_err());
+}
+
+#[test]
+fn pair_state_round_trip() {
+    let state = UniswapV2PairState {
+        factory: Address::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap(),
+        token0: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+        token1: Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap(),
+        reserves: PairReserves::from_slot(rU256::from_str(RESERVES_SLOT_VALUE).unwrap()),
+        price0_cumulative_last: rU256::from(123456789u64),
+        price1_cumulative_last: rU256::from(987654321u64),
+        k_last: rU256::from(42),
+    };
+    let slots: HashMap<rU256, rU256> = state.encode().unwrap().into_iter().collect();
+    assert_eq!(
+        slots[&rU256::from(TOKEN0_SLOT)],
+        rU256::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap()
+    );
+    let decoded = UniswapV2PairState::decode(|slot| Ok::<_, Infallible>(slots[&slot])).unwrap();
+    assert_eq!(decoded, state);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn decoded_slot_matches_get_reserves() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    let reserves = simulator.pair_reserves(pool_address()).unwrap();
+    let (reserve0, reserve1, ts): (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    assert_eq!(
+        reserves,
+        PairReserves {
+            reserve0,
+            reserve1,
+            block_timestamp_last: ts,
+        }
+    );
+
+    // 覆盖储备量后 getReserves 返回新的值
+    let overridden = PairReserves {
+        reserve0: 1_000,
+        reserve1: 2_000,
+        block_timestamp_last: 3,
+    };
+    simulator
+        .set_pair_reserves(pool_address(), overridden)
+        .unwrap();
+    let (reserve0, reserve1, ts): (u128, u128, u32) = simulator
+        .call_method(&pair_contract(), pool_address(), "getReserves", ())
+        .unwrap();
+    assert_eq!((reserve0, reserve1, ts), (1_000, 2_000, 3));
+    assert_eq!(
+        simulator
+            .load_storage(pool_address(), rU256::from(RESERVES_SLOT))
+            .unwrap(),
+        overridden.to_slot().unwrap()
+    );
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use common::{mock_v2::*, *};
+use revm::primitives::U256 as rU256;
+use revm_example::{
+    uniswap_v2::{get_amount_out, RESERVES_SLOT},
+    KnownEvent, MappingSlot, PairReserves, SimulationError, V2Swap,
+};
+
+const RESERVE_A: u128 = 5_000_000_000_000_000_000_000;
+const RESERVE_B: u128 = 12_000_000_000_000;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swap_matches_amount_out_formula() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    deploy_v2(&mut simulator, (RESERVE_A, RESERVE_B), (1, 1));
+    let amount_in = ether(3);
+
+    // 余额槽位自动发现
+    let outcome = simulator
+        .simulate_v2_swap(&V2Swap {
+            pair: PAIR_AB,
+            token_in: TOKEN_A,
+            amount_in,
+            balance_slot: None,
+            amount_out: None,
+            to: TRADER,
+        })
+        .unwrap();
+    let expected =
+        get_amount_out(amount_in, rU256::from(RESERVE_A), rU256::from(RESERVE_B)).unwrap();
+    assert_eq!(outcome.expected_amount_out, expected);
+    assert_eq!(outcome.amount_out(), expected);
+    assert_eq!(
+        (
+            outcome.amount0_in,
+            outcome.amount1_in,
+            outcome.amount0_out,
+            outcome.amount1_out
+        ),
+        (amount_in, rU256::ZERO, rU256::ZERO, expected)
+    );
+
+    // 交易对按执行后的代币余额写回槽位 8
+    let timestamp: u32 = simulator.block_env().timestamp.to();
+    assert_eq!(
+        outcome.reserves_before,
+        PairReserves {
+            reserve0: RESERVE_A,
+            reserve1: RESERVE_B,
+            block_timestamp_last: 0,
+        }
+    );
+    assert_eq!(
+        outcome.reserves_after,
+        PairReserves {
+            reserve0: RESERVE_A + amount_in.to::<u128>(),
+            reserve1: RESERVE_B - expected.to::<u128>(),
+            block_timestamp_last: timestamp,
+        }
+    );
+
+    // 输出代币从交易对转给接收者
+    let transfers: Vec<_> = outcome
+        .logs
+        .iter()
+        .filter_map(|log| match log.event()? {
+            KnownEvent::Transfer { from, to, value } => Some((log.address, from, to, value)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(transfers, vec![(TOKEN_B, PAIR_AB, TRADER, expected)]);
+
+    // 模拟不改变分叉状态
+    assert_eq!(
+        simulator.pair_reserves(PAIR_AB).unwrap(),
+        outcome.reserves_before
+    );
+    assert_eq!(
+        token_balance(&mut simulator, TOKEN_A, PAIR_AB),
+        rU256::from(RESERVE_A)
+    );
+    assert_eq!(token_balance(&mut simulator, TOKEN_B, TRADER), rU256::ZERO);
+    assert_eq!(
+        simulator
+            .load_storage(PAIR_AB, rU256::from(RESERVES_SLOT))
+            .unwrap(),
+        outcome.reserves_before.to_slot().unwrap()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swap_from_token1_and_oversized_request() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    deploy_v2(&mut simulator, (RESERVE_A, RESERVE_B), (1, 1));
+    let amount_in = rU256::from(25_000_000_000u64);
+    let mut swap = V2Swap {
+        pair: PAIR_AB,
+        token_in: TOKEN_B,
+        amount_in,
+        balance_slot: Some(MappingSlot::solidity(BALANCE_SLOT)),
+        amount_out: None,
+        to: TRADER,
+    };
+    let outcome = simulator.simulate_v2_swap(&swap).unwrap();
+    let expected =
+        get_amount_out(amount_in, rU256::from(RESERVE_B), rU256::from(RESERVE_A)).unwrap();
+    assert_eq!(outcome.amount0_out, expected);
+    assert_eq!(outcome.amount1_in, amount_in);
+    assert_eq!(
+        outcome.reserves_after.reserve0,
+        RESERVE_A - expected.to::<u128>()
+    );
+
+    // 多要一个单位会违反恒定乘积，交易对回滚
+    swap.amount_out = Some(expected + rU256::from(1));
+    assert!(matches!(
+        simulator.simulate_v2_swap(&swap),
+        Err(SimulationError::Revert { .. })
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn swap_rejects_overflowing_input() {
+    let mut simulator = replay_simulator(replay_client(), fork_options()).await;
+    deploy_v2(&mut simulator, (RESERVE_A, RESERVE_B), (1, 1));
+    let mut swap = V2Swap {
+        pair: PAIR_AB,
+        token_in: TOKEN_A,
+        amount_in: rU256::MAX,
+        balance_slot: Some(MappingSlot::solidity(BALANCE_SLOT)),
+        amount_out: None,
+        to: TRADER,
+    };
+    assert!(matches!(
+        simulator.simulate_v2_swap(&swap),
+        Err(SimulationError::InvalidArgument(_))
+    ));
+
+    // 交易对的代币余额加上输入数量超出 uint256 时同样报错，余额保持不变
+    let balance_slot = MappingSlot::solidity(BALANCE_SLOT).slot(PAIR_AB);
+    simulator
+        .cache_db_mut()
+        .insert_account_storage(TOKEN_A, balance_slot, rU256::MAX)
+        .unwrap();
+    swap.amount_in = rU256::from(1);
+    assert!(matches!(
+        simulator.simulate_v2_swap(&swap),
+        Err(SimulationError::InvalidArgument(_))
+    ));
+    assert_eq!(token_balance(&mut simulator, TOKEN_A, PAIR_AB), rU256::MAX);
+}